@@ -1,3 +1,4 @@
 fn main() {
-    tonic_build::compile_protos("../proto/tei.proto").unwrap();
+    tonic_build::compile_protos("../proto/search.proto").unwrap();
+    tonic_build::compile_protos("../proto/crawler.proto").unwrap();
 }
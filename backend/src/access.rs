@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+};
+
+use anyhow::Context;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use config::{Config, File};
+use serde::Deserialize;
+use utils::redis::Key;
+
+use crate::{
+    models::error::{ApiError, ErrorType},
+    state::AppState,
+};
+
+/// Header carrying the API key of the client.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Token bucket refilled at `rate` tokens per second up to `burst`, stored as a hash of
+/// its tokens and last update time. Redis time is used so that replicas share a clock.
+///
+/// Returns whether a token was taken, and otherwise the milliseconds until one is
+/// available.
+const TOKEN_BUCKET: &str = r"
+local rate = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) + tonumber(time[2]) / 1000000
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'time')
+local tokens = tonumber(bucket[1]) or burst
+local last = tonumber(bucket[2]) or now
+tokens = math.min(burst, tokens + math.max(0, now - last) * rate)
+local allowed = 0
+local wait = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+else
+  wait = math.ceil((1 - tokens) / rate * 1000)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'time', tostring(now))
+redis.call('EXPIRE', KEYS[1], math.ceil(burst / rate) + 1)
+return {allowed, wait}
+";
+
+/// Requests a client may make per second, with bursts of up to `burst` requests.
+#[derive(Deserialize, Clone, Copy)]
+pub struct Quota {
+    pub rate: f64,
+    pub burst: u64,
+}
+
+/// Entry of the API keys file.
+#[derive(Deserialize)]
+struct KeyEntry {
+    /// Hex SHA-256 digest of the key, the file holds no secret.
+    sha256: String,
+    rate: Option<f64>,
+    burst: Option<u64>,
+    /// Quota of every IP using the key, for keys shared by the clients of a proxy.
+    ip_rate: Option<f64>,
+    ip_burst: Option<u64>,
+    /// Grants access to the admin routes.
+    #[serde(default)]
+    admin: bool,
+}
+
+#[derive(Deserialize)]
+struct KeysFile {
+    /// Entries by client name.
+    keys: HashMap<String, KeyEntry>,
+}
+
+struct Client {
+    name: String,
+    quota: Quota,
+    /// Quota of each IP using the key, so that a leaked key is limited per IP too.
+    ip_quota: Quota,
+    admin: bool,
+}
+
+/// Client authenticated by its API key, added to the extensions of its requests.
+#[derive(Clone)]
+pub struct Caller {
+    pub name: String,
+    pub admin: bool,
+}
+
+/// API keys of the clients and their quotas.
+pub struct ApiKeys {
+    /// Clients by digest of their key.
+    clients: HashMap<String, Client>,
+    /// Whether requests without a key are rejected rather than limited by IP.
+    required: bool,
+}
+
+impl ApiKeys {
+    /// Reads the keys from a TOML, YAML or JSON file, keys without a quota get `default`
+    /// and `default_ip` per IP.
+    pub fn load(
+        path: &Path,
+        default: Quota,
+        default_ip: Quota,
+        required: bool,
+    ) -> anyhow::Result<Self> {
+        let file: KeysFile = Config::builder()
+            .add_source(File::from(path))
+            .build()
+            .and_then(Config::try_deserialize)
+            .context("Failed to read API keys")?;
+        let mut clients = HashMap::new();
+        for (name, entry) in file.keys {
+            let quota = Quota {
+                rate: entry.rate.unwrap_or(default.rate),
+                burst: entry.burst.unwrap_or(default.burst),
+            };
+            let ip_quota = Quota {
+                rate: entry.ip_rate.unwrap_or(default_ip.rate),
+                burst: entry.ip_burst.unwrap_or(default_ip.burst),
+            };
+            anyhow::ensure!(
+                [quota, ip_quota]
+                    .iter()
+                    .all(|quota| quota.rate > 0.0 && quota.burst > 0),
+                "Quotas of `{name}` must be positive"
+            );
+            let client = Client {
+                name,
+                quota,
+                ip_quota,
+                admin: entry.admin,
+            };
+            clients.insert(entry.sha256.to_lowercase(), client);
+        }
+        Ok(Self { clients, required })
+    }
+}
+
+/// Limits the rate of requests of every API key and client IP across replicas.
+///
+/// Redis failures are logged and let requests through, the API stays available without
+/// its limits.
+pub struct RateLimiter {
+    pub client: redis::Client,
+    pub ip_quota: Quota,
+    script: redis::Script,
+}
+
+impl RateLimiter {
+    pub fn new(client: redis::Client, ip_quota: Quota) -> Self {
+        Self {
+            client,
+            ip_quota,
+            script: redis::Script::new(TOKEN_BUCKET),
+        }
+    }
+
+    /// Takes a token from the bucket of `id`, returning the milliseconds to wait if it is
+    /// empty.
+    async fn take(&self, id: &str, quota: Quota) -> Option<u64> {
+        let result = async {
+            let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+            self.script
+                .key(Key::RateLimit(id))
+                .arg(quota.rate)
+                .arg(quota.burst)
+                .invoke_async::<(i64, i64)>(&mut conn)
+                .await
+        }
+        .await;
+        match result {
+            Ok((1, _)) => None,
+            Ok((_, wait)) => Some(wait.max(0) as u64),
+            Err(e) => {
+                tracing::warn!("Failed to check rate limit: {e:#}");
+                None
+            }
+        }
+    }
+}
+
+fn error(error: ErrorType, message: &str) -> ApiError {
+    ApiError {
+        message: message.to_string(),
+        error,
+        fields: Vec::new(),
+    }
+}
+
+/// Address of the client, the first of `x-forwarded-for` when the backend is behind a
+/// trusted proxy.
+fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_forwarded_for: bool) -> IpAddr {
+    let forwarded = trust_forwarded_for
+        .then(|| headers.get("x-forwarded-for"))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|ip| ip.trim().parse().ok());
+    forwarded.unwrap_or(peer.ip())
+}
+
+/// Request refused by [`check`].
+pub struct Rejection {
+    pub error: ApiError,
+    /// Seconds until the client may retry, for requests over their quota.
+    pub retry_after: Option<u64>,
+}
+
+impl From<ApiError> for Rejection {
+    fn from(error: ApiError) -> Self {
+        Self {
+            error,
+            retry_after: None,
+        }
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(seconds) = self.retry_after {
+            headers.insert("retry-after", HeaderValue::from(seconds));
+        }
+        (headers, self.error).into_response()
+    }
+}
+
+/// Authenticates the API key in `headers` and applies the quota of its client, or of the
+/// IP of `peer` without a key. Shared by the REST and gRPC APIs.
+///
+/// Returns the client of the key, if any.
+pub async fn check(
+    state: &AppState,
+    headers: &HeaderMap,
+    peer: SocketAddr,
+) -> Result<Option<Caller>, Rejection> {
+    let digest = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(sha256::digest);
+    let ip = client_ip(headers, peer, state.trust_forwarded_for);
+    // Keyed requests take from the bucket of their key and from a bucket of their IP for
+    // that key, apart from the bucket of the IP for requests without a key.
+    let (buckets, caller) = match (&state.api_keys, digest) {
+        (Some(api_keys), Some(digest)) => match api_keys.clients.get(&digest) {
+            Some(client) => {
+                let caller = Caller {
+                    name: client.name.clone(),
+                    admin: client.admin,
+                };
+                let buckets = vec![
+                    (format!("k:{}", client.name), Some(client.quota)),
+                    (format!("k:{}:ip:{ip}", client.name), Some(client.ip_quota)),
+                ];
+                (buckets, Some(caller))
+            }
+            None => return Err(error(ErrorType::Unauthorized, "Invalid API key").into()),
+        },
+        (Some(api_keys), None) if api_keys.required => {
+            return Err(error(ErrorType::Unauthorized, "Missing API key").into())
+        }
+        _ => (vec![(format!("ip:{ip}"), None)], None),
+    };
+    if let Some(limiter) = &state.rate_limiter {
+        for (id, quota) in buckets {
+            let quota = quota.unwrap_or(limiter.ip_quota);
+            if let Some(wait) = limiter.take(&id, quota).await {
+                return Err(Rejection {
+                    error: error(ErrorType::TooManyRequests, "Rate limit exceeded"),
+                    retry_after: Some(wait.div_ceil(1000).max(1)),
+                });
+            }
+        }
+    }
+    Ok(caller)
+}
+
+/// Applies [`check`] to the requests of the REST API.
+pub async fn limit(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match check(&state, request.headers(), peer).await {
+        Ok(caller) => {
+            if let Some(caller) = caller {
+                request.extensions_mut().insert(caller);
+            }
+            next.run(request).await
+        }
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+/// Restricts routes to the clients with an admin API key, after [`limit`].
+pub async fn admin(request: Request, next: Next) -> Response {
+    match request.extensions().get::<Caller>() {
+        Some(caller) if caller.admin => {
+            tracing::info!(client = caller.name, path = %request.uri().path(), "Admin request");
+            next.run(request).await
+        }
+        Some(_) => error(ErrorType::Forbidden, "API key is not an admin key").into_response(),
+        None => error(ErrorType::Unauthorized, "Missing admin API key").into_response(),
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    Json,
+};
+use futures::StreamExt;
+use rabbitmq_management_client::{api::queue::QueueApi, RabbitMqClient};
+use redis::AsyncCommands;
+use tonic::{transport::Channel, Code};
+use utils::redis::Key;
+
+use crate::{
+    bad_request, internal_error,
+    models::{
+        error::ApiError, DeleteParams, DeleteResponse, DomainQueue, DomainsResponse,
+        RecrawlRequest, RejectedUrl, SeedRequest, SeedResponse,
+    },
+    not_found,
+    state::AppState,
+    validate,
+};
+
+pub mod proto {
+    tonic::include_proto!("crawler");
+}
+
+use proto::{crawler_client::CrawlerClient, CrawlRequest, PageRequest, PrefixRequest};
+
+/// Number of seed URLs crawled concurrently.
+const SEED_CONCURRENCY: usize = 8;
+
+/// Clients of the crawl services the admin routes manage.
+#[derive(Clone)]
+pub struct Admin {
+    pub crawler_client: CrawlerClient<Channel>,
+    /// RabbitMQ management API, which lists the domain queues.
+    pub management_client: Option<RabbitMqClient>,
+    /// Redis holding the domains paused for the selector.
+    pub redis_client: Option<redis::Client>,
+}
+
+fn admin(state: &AppState) -> Result<&Admin, ApiError> {
+    state
+        .admin
+        .as_ref()
+        .ok_or_else(|| not_found("Admin API is disabled".to_string()))
+}
+
+fn crawler_error(status: tonic::Status) -> ApiError {
+    match status.code() {
+        Code::InvalidArgument => bad_request(status.message().to_string()),
+        Code::NotFound => not_found(status.message().to_string()),
+        _ => {
+            tracing::error!("Crawler request failed: {status}");
+            internal_error("Crawler request failed")
+        }
+    }
+}
+
+/// Crawls seed URLs, each indexed before the response.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/seeds",
+    tag = "admin",
+    request_body = SeedRequest,
+    responses(
+        (status = 200, description = "Seeds crawled", body = SeedResponse),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "API key is not an admin key", body = ApiError),
+    ),
+    security(("api_key" = []))
+)]
+#[axum::debug_handler]
+pub async fn seed(
+    State(state): State<AppState>,
+    request: Result<Json<SeedRequest>, JsonRejection>,
+) -> Result<SeedResponse, ApiError> {
+    let Json(request) = request.map_err(|e| bad_request(e.body_text()))?;
+    validate::seed(&request).map_err(ApiError::invalid)?;
+    let admin = admin(&state)?;
+    let outcomes: Vec<_> = futures::stream::iter(request.urls.into_iter().map(|url| {
+        let mut client = admin.crawler_client.clone();
+        async move {
+            let request = CrawlRequest {
+                url: url.clone(),
+                job_id: None,
+            };
+            (url, client.crawl(request).await)
+        }
+    }))
+    .buffer_unordered(SEED_CONCURRENCY)
+    .collect()
+    .await;
+
+    let mut crawled = 0;
+    let mut rejected = Vec::new();
+    for (url, outcome) in outcomes {
+        match outcome {
+            Ok(_) => crawled += 1,
+            Err(status) => {
+                tracing::warn!(url, "Failed to crawl seed: {status}");
+                rejected.push(RejectedUrl {
+                    url,
+                    reason: status.message().to_string(),
+                });
+            }
+        }
+    }
+    tracing::info!(crawled, rejected = rejected.len(), "Crawled seeds");
+    Ok(SeedResponse { crawled, rejected })
+}
+
+async fn paused(admin: &Admin) -> anyhow::Result<HashSet<String>> {
+    let Some(redis_client) = &admin.redis_client else {
+        return Ok(HashSet::new());
+    };
+    let mut conn = redis_client.get_multiplexed_tokio_connection().await?;
+    Ok(conn.smembers(Key::PausedDomains).await?)
+}
+
+/// Domain queues with their depths.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/domains",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Domain queues", body = DomainsResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "API key is not an admin key", body = ApiError),
+    ),
+    security(("api_key" = []))
+)]
+#[axum::debug_handler]
+pub async fn domains(State(state): State<AppState>) -> Result<DomainsResponse, ApiError> {
+    let admin = admin(&state)?;
+    let management_client = admin
+        .management_client
+        .as_ref()
+        .ok_or_else(|| not_found("Listing queues requires RABBITMQ_API_URL".to_string()))?;
+    let queues = management_client.list_queues(None).await.map_err(|e| {
+        tracing::error!("Failed to list queues: {e:#}");
+        internal_error("Failed to list queues")
+    })?;
+    let mut paused = paused(admin).await.map_err(|e| {
+        tracing::error!("Failed to read paused domains: {e:#}");
+        internal_error("Failed to list queues")
+    })?;
+
+    let mut domains: Vec<_> = queues
+        .into_iter()
+        .map(|queue| DomainQueue {
+            paused: paused.remove(&queue.name),
+            domain: queue.name,
+            depth: queue.messages as u64,
+        })
+        .collect();
+    // Queues are deleted once empty, paused domains are listed without one.
+    domains.extend(paused.into_iter().map(|domain| DomainQueue {
+        domain,
+        depth: 0,
+        paused: true,
+    }));
+    domains.sort_by(|a, b| b.depth.cmp(&a.depth).then_with(|| a.domain.cmp(&b.domain)));
+    Ok(DomainsResponse { domains })
+}
+
+async fn set_paused(state: &AppState, domain: String, pause: bool) -> Result<StatusCode, ApiError> {
+    let admin = admin(state)?;
+    let redis_client = admin
+        .redis_client
+        .as_ref()
+        .ok_or_else(|| not_found("Pausing domains requires REDIS_URI".to_string()))?;
+    let domain = domain.to_lowercase();
+    let result = async {
+        let mut conn = redis_client.get_multiplexed_tokio_connection().await?;
+        if pause {
+            conn.sadd::<_, _, ()>(Key::PausedDomains, &domain).await
+        } else {
+            conn.srem::<_, _, ()>(Key::PausedDomains, &domain).await
+        }
+    }
+    .await;
+    result.map_err(|e| {
+        tracing::error!(domain, "Failed to update paused domains: {e:#}");
+        internal_error("Failed to update domain")
+    })?;
+    tracing::info!(domain, pause, "Updated paused domains");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stops the selector from crawling the queue of a domain.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/domains/{domain}/pause",
+    tag = "admin",
+    params(("domain" = String, Path)),
+    responses(
+        (status = 204, description = "Domain paused"),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "API key is not an admin key", body = ApiError),
+    ),
+    security(("api_key" = []))
+)]
+#[axum::debug_handler]
+pub async fn pause(
+    State(state): State<AppState>,
+    Path(domain): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    set_paused(&state, domain, true).await
+}
+
+/// Lets the selector crawl the queue of a paused domain again.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/domains/{domain}/resume",
+    tag = "admin",
+    params(("domain" = String, Path)),
+    responses(
+        (status = 204, description = "Domain resumed"),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "API key is not an admin key", body = ApiError),
+    ),
+    security(("api_key" = []))
+)]
+#[axum::debug_handler]
+pub async fn resume(
+    State(state): State<AppState>,
+    Path(domain): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    set_paused(&state, domain, false).await
+}
+
+/// Crawls a URL again even if it was crawled recently.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/recrawl",
+    tag = "admin",
+    request_body = RecrawlRequest,
+    responses(
+        (status = 204, description = "URL crawled"),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "API key is not an admin key", body = ApiError),
+    ),
+    security(("api_key" = []))
+)]
+#[axum::debug_handler]
+pub async fn recrawl(
+    State(state): State<AppState>,
+    request: Result<Json<RecrawlRequest>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let Json(request) = request.map_err(|e| bad_request(e.body_text()))?;
+    validate::recrawl(&request).map_err(ApiError::invalid)?;
+    let admin = admin(&state)?;
+    let request = CrawlRequest {
+        url: request.url,
+        job_id: None,
+    };
+    admin
+        .crawler_client
+        .clone()
+        .recrawl(request)
+        .await
+        .map_err(crawler_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes a page, or every page under a URL prefix, from the index.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/pages",
+    tag = "admin",
+    params(DeleteParams),
+    responses(
+        (status = 200, description = "Pages deleted", body = DeleteResponse),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 403, description = "API key is not an admin key", body = ApiError),
+        (status = 404, description = "Unknown URL", body = ApiError),
+    ),
+    security(("api_key" = []))
+)]
+#[axum::debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    params: Result<Query<DeleteParams>, QueryRejection>,
+) -> Result<DeleteResponse, ApiError> {
+    let Query(params) = params.map_err(|e| bad_request(e.body_text()))?;
+    validate::delete(&params).map_err(ApiError::invalid)?;
+    let mut client = admin(&state)?.crawler_client.clone();
+    let deleted = match (params.url, params.prefix) {
+        (Some(url), None) => {
+            client
+                .delete_page(PageRequest { url })
+                .await
+                .map_err(crawler_error)?;
+            1
+        }
+        (None, Some(prefix)) => {
+            client
+                .delete_prefix(PrefixRequest { prefix })
+                .await
+                .map_err(crawler_error)?
+                .into_inner()
+                .deleted
+        }
+        _ => {
+            return Err(bad_request(
+                "Exactly one of `url` and `prefix` must be set".to_string(),
+            ))
+        }
+    };
+    Ok(DeleteResponse { deleted })
+}
@@ -0,0 +1,81 @@
+use anyhow::Context;
+use axum::http::HeaderMap;
+use mongodb::{
+    bson::{doc, Uuid},
+    Client,
+};
+use mongodm::{f, sync_indexes, ToRepository};
+use utils::database::{Click, ClicksCollConf, QueriesCollConf, QueryLog, DATABASE};
+
+/// Header carrying the id of the session of the client, recorded with its searches and
+/// clicks.
+pub const SESSION_HEADER: &str = "x-session-id";
+/// Maximum number of characters of a session id.
+const MAX_SESSION_LENGTH: usize = 128;
+
+pub fn session(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|session| !session.is_empty() && session.len() <= MAX_SESSION_LENGTH)
+        .map(str::to_string)
+}
+
+/// Writer of the query log and clicks, which expire after
+/// [`utils::database::ANALYTICS_TTL_SECS`].
+#[derive(Clone)]
+pub struct Analytics {
+    client: Client,
+}
+
+impl Analytics {
+    pub async fn connect(uri: &str) -> anyhow::Result<Self> {
+        let client = Client::with_uri_str(uri)
+            .await
+            .context("Failed to connect to MongoDB")?;
+        let db = client.database(DATABASE);
+        sync_indexes::<QueriesCollConf>(&db)
+            .await
+            .context("Failed to create query log indexes")?;
+        sync_indexes::<ClicksCollConf>(&db)
+            .await
+            .context("Failed to create click indexes")?;
+        Ok(Self { client })
+    }
+
+    /// Records a search in the background, searches never wait for nor fail because of
+    /// the log.
+    pub fn log_query(&self, entry: QueryLog) {
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let result = client
+                .database(DATABASE)
+                .repository::<QueryLog>()
+                .insert_one(entry)
+                .await;
+            if let Err(e) = result {
+                tracing::warn!("Failed to log query: {e:#}");
+            }
+        });
+    }
+
+    pub async fn find_query(&self, uuid: Uuid) -> anyhow::Result<Option<QueryLog>> {
+        self.client
+            .database(DATABASE)
+            .repository::<QueryLog>()
+            .find_one(doc! { f!(uuid in QueryLog): uuid })
+            .await
+            .context("Failed to find query log entry")
+    }
+
+    pub async fn log_click(&self, click: Click) -> anyhow::Result<()> {
+        self.client
+            .database(DATABASE)
+            .repository::<Click>()
+            .insert_one(click)
+            .await
+            .context("Failed to log click")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use std::{cell::Cell, collections::HashMap, path::PathBuf, process::ExitCode, rc::Rc};
+
+use anyhow::Context;
+use chrono::Utc;
+use clap::Parser;
+use mongodb::{
+    bson::{doc, DateTime, Uuid},
+    Client,
+};
+use mongodm::{f, ToRepository};
+use utils::{
+    database::{Click, QueryLog, DATABASE},
+    ltr::{Example, Model, TrainConfig, FEATURES},
+};
+
+/// Trains the learned ranking model of the backend from the query log and clicks.
+///
+/// Every search with clicks gives an example per result down to the last clicked one:
+/// clicked results are positive, the results shown above or between them negative.
+/// The model is written to `output`, which the backend reloads when it changes.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// File the model is written to
+    #[arg(long, env = "LTR_MODEL")]
+    output: PathBuf,
+    /// Number of days of searches trained on
+    #[arg(long, default_value_t = 30)]
+    days: i64,
+    /// Number of gradient descent steps
+    #[arg(long, default_value_t = 500)]
+    epochs: usize,
+    #[arg(long, default_value_t = 0.1)]
+    learning_rate: f32,
+    /// L2 regularization strength
+    #[arg(long, default_value_t = 0.001)]
+    l2: f32,
+    #[arg(long, env)]
+    mongo_uri_read: String,
+}
+
+/// Clicked pages of the searches since `since`, by search id.
+async fn clicks(
+    client: &Client,
+    since: chrono::DateTime<Utc>,
+) -> anyhow::Result<HashMap<Uuid, Vec<String>>> {
+    let since = DateTime::from_millis(since.timestamp_millis());
+    let mut cursor = client
+        .database(DATABASE)
+        .repository::<Click>()
+        .find(doc! { f!(time in Click): { "$gte": since } })
+        .await?;
+    let mut clicks: HashMap<Uuid, Vec<String>> = HashMap::new();
+    while cursor.advance().await? {
+        let click: Click = cursor.deserialize_current()?;
+        clicks.entry(click.search).or_default().push(click.page);
+    }
+    Ok(clicks)
+}
+
+/// Examples of the searches since `since` with clicks.
+async fn examples(client: &Client, since: chrono::DateTime<Utc>) -> anyhow::Result<Vec<Example>> {
+    let clicks = clicks(client, since)
+        .await
+        .context("Failed to read clicks")?;
+    let since = DateTime::from_millis(since.timestamp_millis());
+    let mut cursor = client
+        .database(DATABASE)
+        .repository::<QueryLog>()
+        .find(doc! {
+            f!(time in QueryLog): { "$gte": since },
+            f!(features in QueryLog): { "$exists": true },
+        })
+        .await
+        .context("Failed to read the query log")?;
+    let mut examples = Vec::new();
+    while cursor.advance().await? {
+        let search: QueryLog = cursor.deserialize_current()?;
+        let Some(clicked) = clicks.get(&search.uuid) else {
+            continue;
+        };
+        let Some(last) = search
+            .results
+            .iter()
+            .rposition(|page| clicked.contains(page))
+        else {
+            continue;
+        };
+        let results = search.results.iter().zip(search.features).take(last + 1);
+        for (page, features) in results {
+            // Results logged without features, which were not ranked.
+            if features.len() != FEATURES.len() {
+                continue;
+            }
+            examples.push(Example {
+                features,
+                clicked: clicked.contains(page),
+            });
+        }
+    }
+    Ok(examples)
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
+    let client = Client::with_uri_str(&args.mongo_uri_read)
+        .await
+        .context("Failed to connect to MongoDB")?;
+    let since = Utc::now() - chrono::Duration::days(args.days);
+    let examples = examples(&client, since)
+        .await
+        .context("Failed to collect examples")?;
+    let clicked = examples.iter().filter(|e| e.clicked).count();
+    tracing::info!(
+        examples = examples.len(),
+        clicked,
+        "Collected examples of the last {} days",
+        args.days
+    );
+
+    let config = TrainConfig {
+        epochs: args.epochs,
+        learning_rate: args.learning_rate,
+        l2: args.l2,
+    };
+    let model = Model::train(&examples, &config).context("Failed to train model")?;
+    tracing::info!(
+        loss = model.loss(&examples),
+        weights = ?model.weights,
+        "Trained model"
+    );
+    model.save(&args.output).context("Failed to save model")?;
+    tracing::info!(output = %args.output.display(), "Saved model");
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let succeeded = Rc::new(Cell::new(false));
+    let done = succeeded.clone();
+    let task = async move {
+        match run(args).await {
+            Ok(()) => done.set(true),
+            Err(e) => tracing::error!("{e:#}"),
+        }
+    };
+    // Errors are logged within `start`, before its exporters are shut down, and then fail
+    // the process so that the scheduler running it notices.
+    utils::start("train-ranker", Box::pin(task)).await;
+    if succeeded.get() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
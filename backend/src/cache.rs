@@ -0,0 +1,122 @@
+use redis::AsyncCommands;
+use serde::{de::DeserializeOwned, Serialize};
+use utils::redis::Key;
+
+use crate::models::SearchResponse;
+
+/// Header telling whether the results were served from the cache.
+pub const RESULTS_HEADER: &str = "x-cache-results";
+/// Header telling whether the query embedding was served from the cache.
+pub const EMBEDDING_HEADER: &str = "x-cache-embedding";
+
+pub fn status(hit: bool) -> &'static str {
+    if hit {
+        "hit"
+    } else {
+        "miss"
+    }
+}
+
+/// Trims `query` and collapses its whitespace.
+pub fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Redis cache of query embeddings and result pages.
+///
+/// Failures are logged and treated as misses, searches never fail because of the cache.
+#[derive(Clone)]
+pub struct Cache {
+    pub client: redis::Client,
+    pub embedding_ttl: u64,
+    pub results_ttl: u64,
+    /// Number of index changes counted by the crawler after which cached results expire.
+    pub version_step: u64,
+}
+
+impl Cache {
+    async fn get<T: DeserializeOwned>(&self, key: Key<'_>) -> Option<T> {
+        let result = async {
+            let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+            conn.get::<_, Option<String>>(key).await
+        }
+        .await;
+        match result {
+            Ok(value) => value.and_then(|value| match serde_json::from_str(&value) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    tracing::warn!("Failed to parse cached value: {e:#}");
+                    None
+                }
+            }),
+            Err(e) => {
+                tracing::warn!("Failed to GET from Redis: {e:#}");
+                None
+            }
+        }
+    }
+
+    async fn set<T: Serialize + ?Sized>(&self, key: Key<'_>, value: &T, ttl: u64) {
+        let value = match serde_json::to_string(value) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!("Failed to serialize cached value: {e:#}");
+                return;
+            }
+        };
+        let result = async {
+            let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+            conn.set_ex::<_, _, ()>(key, value, ttl).await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to SET in Redis: {e:#}");
+        }
+    }
+
+    fn embedding_digest(model_id: &str, query: &str) -> String {
+        sha256::digest(format!("{model_id}\n{}", normalize(query)))
+    }
+
+    pub async fn embedding(&self, model_id: &str, query: &str) -> Option<Vec<f32>> {
+        let digest = Self::embedding_digest(model_id, query);
+        self.get(Key::Embedding(&digest)).await
+    }
+
+    pub async fn set_embedding(&self, model_id: &str, query: &str, embedding: &[f32]) {
+        let digest = Self::embedding_digest(model_id, query);
+        self.set(Key::Embedding(&digest), embedding, self.embedding_ttl)
+            .await
+    }
+
+    /// Version of the index, which changes every `version_step` changes.
+    pub async fn version(&self) -> Option<u64> {
+        let result = async {
+            let mut conn = self.client.get_multiplexed_tokio_connection().await?;
+            conn.get::<_, Option<u64>>(Key::IndexChanges).await
+        }
+        .await;
+        match result {
+            Ok(changes) => Some(changes.unwrap_or(0) / self.version_step.max(1)),
+            Err(e) => {
+                tracing::warn!("Failed to GET index changes from Redis: {e:#}");
+                None
+            }
+        }
+    }
+
+    /// Digest identifying a page of results of `request` for an index version.
+    pub fn results_digest<T: Serialize>(model_id: &str, version: u64, request: &T) -> String {
+        let request = serde_json::to_string(request).unwrap_or_default();
+        sha256::digest(format!("{model_id}\n{version}\n{request}"))
+    }
+
+    pub async fn results(&self, digest: &str) -> Option<SearchResponse> {
+        self.get(Key::Results(digest)).await
+    }
+
+    pub async fn set_results(&self, digest: &str, response: &SearchResponse) {
+        self.set(Key::Results(digest), response, self.results_ttl)
+            .await
+    }
+}
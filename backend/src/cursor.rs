@@ -0,0 +1,145 @@
+use anyhow::Context;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use qdrant_client::qdrant::ScoredPoint;
+use serde::{Deserialize, Serialize};
+
+use crate::models::point_id;
+
+/// End of a page of results, handed to clients as an opaque token to fetch the next one.
+///
+/// A cursor keeps pages stable, not cheap: the next page is still found in the ranking
+/// of its first `offset + limit + 1` results. Its score is a fused score, derived from
+/// the ranks of the results in the dense and sparse rankings, so it cannot bound either
+/// search; the depth of cursors is limited by `MAX_OFFSET` like offsets.
+#[derive(Serialize, Deserialize)]
+pub struct Cursor {
+    /// Number of results before the next page when the cursor was issued.
+    pub offset: u64,
+    /// UUID of the last result of the page.
+    pub id: String,
+    /// Score of the last result of the page.
+    pub score: f32,
+}
+
+impl Cursor {
+    /// Cursor of a page ending with `point`, `offset` results into the ranking.
+    pub fn after(point: &ScoredPoint, offset: u64) -> Option<Self> {
+        Some(Self {
+            offset,
+            id: point_id(&point.id)?,
+            score: point.score,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> anyhow::Result<Self> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token)
+            .context("Invalid cursor encoding")?;
+        serde_json::from_slice(&bytes).context("Invalid cursor")
+    }
+
+    /// Position in `points` of the first result of the next page.
+    ///
+    /// The page starts after the last result of the previous one if it is still ranked,
+    /// so results moving in the index between requests are neither repeated nor skipped.
+    /// Otherwise it starts at the first result scoring less when `points` are ordered by
+    /// score, or at the previous offset when they are not.
+    pub fn start(&self, points: &[ScoredPoint], by_score: bool) -> usize {
+        let last = points
+            .iter()
+            .position(|point| point_id(&point.id).as_deref() == Some(self.id.as_str()));
+        match last {
+            Some(last) => last + 1,
+            None if by_score => points
+                .iter()
+                .position(|point| point.score < self.score)
+                .unwrap_or(points.len()),
+            None => (self.offset as usize).min(points.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qdrant_client::qdrant::{point_id::PointIdOptions, PointId};
+
+    use super::*;
+
+    fn point(id: &str, score: f32) -> ScoredPoint {
+        ScoredPoint {
+            id: Some(PointId {
+                point_id_options: Some(PointIdOptions::Uuid(id.to_string())),
+            }),
+            score,
+            ..Default::default()
+        }
+    }
+
+    fn cursor(offset: u64, id: &str, score: f32) -> Cursor {
+        Cursor {
+            offset,
+            id: id.to_string(),
+            score,
+        }
+    }
+
+    fn points() -> Vec<ScoredPoint> {
+        vec![
+            point("a", 0.9),
+            point("b", 0.7),
+            point("c", 0.5),
+            point("d", 0.3),
+        ]
+    }
+
+    #[test]
+    fn starts_after_last_result() {
+        let cursor = cursor(3, "b", 0.7);
+        assert_eq!(cursor.start(&points(), true), 2);
+        assert_eq!(cursor.start(&points(), false), 2);
+    }
+
+    #[test]
+    fn missing_last_result_by_score() {
+        assert_eq!(cursor(1, "x", 0.6).start(&points(), true), 2);
+        // Results scoring the same as the last one were on the previous page.
+        assert_eq!(cursor(1, "x", 0.5).start(&points(), true), 3);
+        assert_eq!(cursor(1, "x", 0.1).start(&points(), true), 4);
+        assert_eq!(cursor(1, "x", 1.0).start(&points(), true), 0);
+    }
+
+    #[test]
+    fn missing_last_result_by_offset() {
+        assert_eq!(cursor(1, "x", 0.6).start(&points(), false), 1);
+        assert_eq!(cursor(10, "x", 0.6).start(&points(), false), 4);
+        assert_eq!(cursor(1, "x", 0.6).start(&[], false), 0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let token = Cursor::after(&point("b", 0.7), 2).unwrap().encode();
+        let decoded = Cursor::decode(&token).unwrap();
+        assert_eq!(decoded.offset, 2);
+        assert_eq!(decoded.id, "b");
+        assert_eq!(decoded.score, 0.7);
+    }
+
+    #[test]
+    fn invalid_tokens() {
+        assert!(Cursor::decode("not a cursor!").is_err());
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode(b"{}")).is_err());
+    }
+
+    #[test]
+    fn point_without_id() {
+        let point = ScoredPoint {
+            score: 0.5,
+            ..Default::default()
+        };
+        assert!(Cursor::after(&point, 1).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use qdrant_client::qdrant::{value::Kind, vectors::VectorsOptions, PointGroup, ScoredPoint};
+
+fn domain(point: &ScoredPoint) -> Option<&str> {
+    match point.payload.get("domain")?.kind.as_ref()? {
+        Kind::StringValue(domain) => Some(domain),
+        _ => None,
+    }
+}
+
+/// Hits of the groups, best first.
+pub fn flatten(groups: Vec<PointGroup>) -> Vec<ScoredPoint> {
+    let mut points: Vec<_> = groups.into_iter().flat_map(|group| group.hits).collect();
+    points.sort_by(|a, b| b.score.total_cmp(&a.score));
+    points
+}
+
+/// Drops the points of a domain beyond its first `max`.
+///
+/// Each ranking is grouped by Qdrant, this keeps the fused ranking within the limit too.
+pub fn cap_per_domain(points: &mut Vec<ScoredPoint>, max: u32) {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    points.retain(|point| match domain(point) {
+        Some(domain) => {
+            let count = counts.entry(domain.to_string()).or_default();
+            *count += 1;
+            *count <= max
+        }
+        None => true,
+    });
+}
+
+/// Dense embedding of the point, the unnamed vector of the collection.
+fn dense(point: &ScoredPoint) -> Option<&[f32]> {
+    match point.vectors.as_ref()?.vectors_options.as_ref()? {
+        VectorsOptions::Vector(vector) => Some(&vector.data),
+        VectorsOptions::Vectors(named) => named.vectors.get("").map(|vector| &vector.data[..]),
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm = |v: &[f32]| v.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms > 0.0 {
+        dot / norms
+    } else {
+        0.0
+    }
+}
+
+/// Reorders the first `count` points by maximal marginal relevance.
+///
+/// Each pick maximizes `lambda * relevance - (1 - lambda) * similarity`, where relevance
+/// is the score of the point scaled to `[0, 1]` and similarity is the highest cosine
+/// similarity of its dense embedding to the points already picked. Points without an
+/// embedding are never considered similar. The remaining points follow in their
+/// original order.
+///
+/// The highest similarity of every candidate is updated against each new pick only, so
+/// that reordering `count` of `n` points takes `count * n` cosines.
+pub fn mmr(points: Vec<ScoredPoint>, lambda: f32, count: usize) -> Vec<ScoredPoint> {
+    let (min, max) = points
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), point| {
+            (min.min(point.score), max.max(point.score))
+        });
+    let relevance: Vec<f32> = points
+        .iter()
+        .map(|point| {
+            if max > min {
+                (point.score - min) / (max - min)
+            } else {
+                1.0
+            }
+        })
+        .collect();
+
+    let mut similarity = vec![0.0f32; points.len()];
+    let mut picked = vec![false; points.len()];
+    let mut order = Vec::with_capacity(count.min(points.len()));
+    while order.len() < count {
+        let best = (0..points.len())
+            .filter(|&i| !picked[i])
+            .map(|i| (i, lambda * relevance[i] - (1.0 - lambda) * similarity[i]))
+            // The first of equally good points wins.
+            .min_by(|(_, a), (_, b)| b.total_cmp(a));
+        let Some((best, _)) = best else {
+            break;
+        };
+        picked[best] = true;
+        order.push(best);
+        let Some(vector) = dense(&points[best]) else {
+            continue;
+        };
+        for (i, point) in points.iter().enumerate() {
+            if let (false, Some(other)) = (picked[i], dense(point)) {
+                similarity[i] = similarity[i].max(cosine(vector, other));
+            }
+        }
+    }
+
+    let mut remaining: Vec<_> = points.into_iter().map(Some).collect();
+    let mut selected: Vec<ScoredPoint> = order
+        .into_iter()
+        .filter_map(|i| remaining[i].take())
+        .collect();
+    selected.extend(remaining.into_iter().flatten());
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use qdrant_client::qdrant::{point_id::PointIdOptions, PointId, Value, Vector, Vectors};
+
+    use super::*;
+
+    fn point(id: &str, score: f32, domain: Option<&str>, vector: Option<&[f32]>) -> ScoredPoint {
+        let payload = domain
+            .map(|domain| {
+                let value = Value {
+                    kind: Some(Kind::StringValue(domain.to_string())),
+                };
+                HashMap::from([("domain".to_string(), value)])
+            })
+            .unwrap_or_default();
+        let vectors = vector.map(|data| Vectors {
+            vectors_options: Some(VectorsOptions::Vector(Vector {
+                data: data.to_vec(),
+                ..Default::default()
+            })),
+        });
+        ScoredPoint {
+            id: Some(PointId {
+                point_id_options: Some(PointIdOptions::Uuid(id.to_string())),
+            }),
+            score,
+            payload,
+            vectors,
+            ..Default::default()
+        }
+    }
+
+    fn ids(points: &[ScoredPoint]) -> Vec<&str> {
+        points
+            .iter()
+            .filter_map(
+                |point| match point.id.as_ref()?.point_id_options.as_ref()? {
+                    PointIdOptions::Uuid(id) => Some(id.as_str()),
+                    PointIdOptions::Num(_) => None,
+                },
+            )
+            .collect()
+    }
+
+    /// `a` and its duplicate `b` rank above `c`, which is about something else.
+    fn duplicates() -> Vec<ScoredPoint> {
+        vec![
+            point("a", 0.9, None, Some(&[1.0, 0.0])),
+            point("b", 0.8, None, Some(&[1.0, 0.0])),
+            point("c", 0.7, None, Some(&[0.0, 1.0])),
+        ]
+    }
+
+    #[test]
+    fn cap_per_domain_keeps_first_of_each_domain() {
+        let mut points = vec![
+            point("a", 0.9, Some("example.com"), None),
+            point("b", 0.8, Some("example.com"), None),
+            point("c", 0.7, Some("other.org"), None),
+            point("d", 0.6, Some("example.com"), None),
+            point("e", 0.5, None, None),
+        ];
+        cap_per_domain(&mut points, 2);
+        assert_eq!(ids(&points), ["a", "b", "c", "e"]);
+        cap_per_domain(&mut points, 1);
+        assert_eq!(ids(&points), ["a", "c", "e"]);
+        // Points without a domain are never dropped.
+        cap_per_domain(&mut points, 0);
+        assert_eq!(ids(&points), ["e"]);
+    }
+
+    #[test]
+    fn mmr_relevance_only() {
+        assert_eq!(ids(&mmr(duplicates(), 1.0, 3)), ["a", "b", "c"]);
+        let mut points = duplicates();
+        points.reverse();
+        assert_eq!(ids(&mmr(points, 1.0, 3)), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn mmr_diversity_only() {
+        // All points are equally relevant, the first wins and then the least similar.
+        assert_eq!(ids(&mmr(duplicates(), 0.0, 3)), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn mmr_balanced() {
+        // `b` is half as relevant as `a` above `c` but identical to `a`.
+        assert_eq!(ids(&mmr(duplicates(), 0.5, 3)), ["a", "c", "b"]);
+        assert_eq!(ids(&mmr(duplicates(), 0.9, 3)), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn mmr_keeps_remaining_order() {
+        let mut points = duplicates();
+        points.reverse();
+        assert_eq!(ids(&mmr(points, 1.0, 1)), ["a", "c", "b"]);
+        assert_eq!(ids(&mmr(duplicates(), 0.0, 0)), ["a", "b", "c"]);
+        assert_eq!(ids(&mmr(duplicates(), 0.0, 10)), ["a", "c", "b"]);
+        assert!(mmr(Vec::new(), 0.5, 10).is_empty());
+    }
+
+    #[test]
+    fn mmr_without_embeddings() {
+        let points = vec![
+            point("a", 0.9, None, Some(&[1.0, 0.0])),
+            point("b", 0.8, None, None),
+            point("c", 0.7, None, Some(&[1.0, 0.0])),
+        ];
+        assert_eq!(ids(&mmr(points, 0.0, 3)), ["a", "b", "c"]);
+    }
+}
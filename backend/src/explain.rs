@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use qdrant_client::qdrant::ScoredPoint;
+
+use crate::models::{point_id, Component, Explanation};
+
+/// Scores and ranks of the points in a ranking, by point id.
+pub fn ranks(points: &[ScoredPoint]) -> HashMap<String, Component> {
+    points
+        .iter()
+        .enumerate()
+        .filter_map(|(rank, point)| {
+            let component = Component {
+                score: point.score,
+                rank: rank + 1,
+            };
+            Some((point_id(&point.id)?, component))
+        })
+        .collect()
+}
+
+/// Rankings a search went through, recorded when the request asks for an explanation or
+/// the ranking features are needed.
+#[derive(Default)]
+pub struct Stages {
+    pub dense: HashMap<String, Component>,
+    pub sparse: HashMap<String, Component>,
+    pub fused: HashMap<String, Component>,
+    pub rerank: HashMap<String, Component>,
+    pub ltr: HashMap<String, Component>,
+}
+
+impl Stages {
+    pub fn explain(&self, id: &str) -> Explanation {
+        Explanation {
+            dense: self.dense.get(id).copied(),
+            sparse: self.sparse.get(id).copied(),
+            fused: self.fused.get(id).copied(),
+            rerank: self.rerank.get(id).copied(),
+            ltr: self.ltr.get(id).copied(),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::{Condition, DatetimeRange, Filter};
+
+use crate::models::{DateRange, SearchFilter};
+
+fn datetime_range(field: &str, range: &DateRange) -> Condition {
+    let timestamp = |t: DateTime<Utc>| prost_types::Timestamp::from(SystemTime::from(t));
+    Condition::datetime_range(
+        field,
+        DatetimeRange {
+            gte: range.from.map(timestamp),
+            lte: range.to.map(timestamp),
+            ..Default::default()
+        },
+    )
+}
+
+impl SearchFilter {
+    /// Qdrant filter on the payload written by the crawler, matching every point if no
+    /// criterion is set.
+    pub fn to_qdrant(&self) -> Filter {
+        let mut must = Vec::new();
+        let mut must_not = Vec::new();
+        if !self.domains.is_empty() {
+            must.push(Condition::matches("domain", self.domains.clone()));
+        }
+        if !self.exclude_domains.is_empty() {
+            must_not.push(Condition::matches("domain", self.exclude_domains.clone()));
+        }
+        if !self.lang.is_empty() {
+            must.push(Condition::matches("lang", self.lang.clone()));
+        }
+        if !self.mime.is_empty() {
+            must.push(Condition::matches("mime", self.mime.clone()));
+        }
+        if let Some(first) = &self.first {
+            must.push(datetime_range("first", first));
+        }
+        if let Some(last) = &self.last {
+            must.push(datetime_range("last", last));
+        }
+        Filter {
+            must,
+            must_not,
+            ..Default::default()
+        }
+    }
+}
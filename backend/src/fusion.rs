@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use qdrant_client::qdrant::ScoredPoint;
+
+use crate::models::point_id;
+
+/// Rank constant of reciprocal rank fusion, damping the weight of the top ranks.
+const RRF_K: f32 = 60.0;
+
+/// Merges ranked lists with weighted reciprocal rank fusion.
+///
+/// Every point scores `weight / (RRF_K + rank)` for each list it appears in. The
+/// returned points are ordered by their fused score, which replaces their original one,
+/// and points scoring the same keep the order in which the lists first have them.
+pub fn rrf(lists: Vec<(f32, Vec<ScoredPoint>)>) -> Vec<ScoredPoint> {
+    let mut fused: Vec<ScoredPoint> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (weight, points) in lists {
+        for (rank, point) in points.into_iter().enumerate() {
+            let Some(key) = point_id(&point.id) else {
+                continue;
+            };
+            let score = weight / (RRF_K + rank as f32 + 1.0);
+            match positions.get(&key) {
+                Some(&position) => fused[position].score += score,
+                None => {
+                    positions.insert(key, fused.len());
+                    fused.push(ScoredPoint { score, ..point });
+                }
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use qdrant_client::qdrant::{point_id::PointIdOptions, PointId};
+
+    use super::*;
+
+    fn point(id: &str) -> ScoredPoint {
+        ScoredPoint {
+            id: Some(PointId {
+                point_id_options: Some(PointIdOptions::Uuid(id.to_string())),
+            }),
+            score: 1.0,
+            ..Default::default()
+        }
+    }
+
+    fn ranking(ids: &[&str]) -> Vec<ScoredPoint> {
+        ids.iter().map(|id| point(id)).collect()
+    }
+
+    fn ids(points: &[ScoredPoint]) -> Vec<String> {
+        points
+            .iter()
+            .filter_map(|point| point_id(&point.id))
+            .collect()
+    }
+
+    #[test]
+    fn weighted_order() {
+        let fused = rrf(vec![
+            (1.0, ranking(&["a", "b"])),
+            (3.0, ranking(&["b", "c"])),
+        ]);
+        assert_eq!(ids(&fused), ["b", "c", "a"]);
+        assert_eq!(fused[0].score, 1.0 / 62.0 + 3.0 / 61.0);
+        assert_eq!(fused[1].score, 3.0 / 62.0);
+        assert_eq!(fused[2].score, 1.0 / 61.0);
+    }
+
+    #[test]
+    fn weight_decides_between_lists() {
+        let lists = |dense, sparse| vec![(dense, ranking(&["a"])), (sparse, ranking(&["b"]))];
+        assert_eq!(ids(&rrf(lists(1.0, 2.0))), ["b", "a"]);
+        assert_eq!(ids(&rrf(lists(2.0, 1.0))), ["a", "b"]);
+    }
+
+    #[test]
+    fn ties_keep_first_seen_order() {
+        let fused = rrf(vec![
+            (1.0, ranking(&["a", "c"])),
+            (1.0, ranking(&["b", "d"])),
+        ]);
+        assert_eq!(ids(&fused), ["a", "b", "c", "d"]);
+        let fused = rrf(vec![
+            (1.0, ranking(&["b", "d"])),
+            (1.0, ranking(&["a", "c"])),
+        ]);
+        assert_eq!(ids(&fused), ["b", "a", "d", "c"]);
+    }
+
+    #[test]
+    fn empty_ranking() {
+        let fused = rrf(vec![(1.0, Vec::new()), (0.5, ranking(&["a", "b"]))]);
+        assert_eq!(ids(&fused), ["a", "b"]);
+        assert_eq!(fused[0].score, 0.5 / 61.0);
+        assert!(rrf(vec![(1.0, Vec::new()), (1.0, Vec::new())]).is_empty());
+    }
+
+    #[test]
+    fn points_without_id_are_skipped() {
+        let without_id = ScoredPoint::default();
+        let fused = rrf(vec![(1.0, vec![without_id, point("a")])]);
+        assert_eq!(ids(&fused), ["a"]);
+        assert_eq!(fused[0].score, 1.0 / 62.0);
+    }
+}
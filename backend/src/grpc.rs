@@ -0,0 +1,299 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use prost_types::Timestamp;
+use tonic::{metadata::MetadataValue, Code, Request, Response, Status};
+use utils::database;
+
+use crate::{
+    access, analytics,
+    models::{
+        error::{ApiError, ErrorType},
+        Component, DateRange, Explanation, MatchResult, PageResponse, QueryExplanation,
+        SearchFilter, SearchRequest, SearchResponse, SimilarRequest, Snippet,
+    },
+    state::AppState,
+    validate,
+};
+
+pub mod proto {
+    tonic::include_proto!("search");
+}
+
+use proto::search_server::Search;
+
+/// gRPC front of the search, sharing the validation, handlers, API keys and rate limits
+/// of the REST API.
+pub struct SearchService {
+    pub state: AppState,
+}
+
+impl SearchService {
+    /// Authenticates and rate limits a request like [`access::limit`] does.
+    async fn check<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        let headers = request.metadata().clone().into_headers();
+        let peer = request
+            .remote_addr()
+            .unwrap_or(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)));
+        match access::check(&self.state, &headers, peer).await {
+            Ok(_) => Ok(()),
+            Err(rejection) => {
+                let mut status = status(rejection.error);
+                if let Some(seconds) = rejection.retry_after {
+                    status
+                        .metadata_mut()
+                        .insert("retry-after", MetadataValue::from(seconds));
+                }
+                Err(status)
+            }
+        }
+    }
+}
+
+fn status(error: ApiError) -> Status {
+    let code = match error.error {
+        ErrorType::BadRequest => Code::InvalidArgument,
+        ErrorType::Unauthorized => Code::Unauthenticated,
+        ErrorType::Forbidden => Code::PermissionDenied,
+        ErrorType::NotFound => Code::NotFound,
+        ErrorType::TooManyRequests => Code::ResourceExhausted,
+        ErrorType::InternalServerError => Code::Internal,
+    };
+    let mut message = error.message;
+    for field in error.fields {
+        message.push_str(&format!("; {}: {}", field.field, field.message));
+    }
+    Status::new(code, message)
+}
+
+fn datetime(timestamp: Option<Timestamp>) -> Result<Option<DateTime<Utc>>, Status> {
+    timestamp
+        .map(|timestamp| {
+            SystemTime::try_from(timestamp)
+                .map(DateTime::from)
+                .map_err(|e| Status::invalid_argument(format!("Invalid timestamp: {e}")))
+        })
+        .transpose()
+}
+
+fn timestamp(datetime: DateTime<Utc>) -> Timestamp {
+    SystemTime::from(datetime).into()
+}
+
+fn date_range(range: Option<proto::DateRange>) -> Result<Option<DateRange>, Status> {
+    range
+        .map(|range| {
+            Ok(DateRange {
+                from: datetime(range.from)?,
+                to: datetime(range.to)?,
+            })
+        })
+        .transpose()
+}
+
+fn filter(filter: Option<proto::Filter>) -> Result<SearchFilter, Status> {
+    let Some(filter) = filter else {
+        return Ok(SearchFilter::default());
+    };
+    Ok(SearchFilter {
+        domains: filter.domains,
+        exclude_domains: filter.exclude_domains,
+        lang: filter.lang,
+        mime: filter.mime,
+        first: date_range(filter.first)?,
+        last: date_range(filter.last)?,
+    })
+}
+
+impl From<SearchFilter> for proto::Filter {
+    fn from(filter: SearchFilter) -> Self {
+        let range = |range: DateRange| proto::DateRange {
+            from: range.from.map(timestamp),
+            to: range.to.map(timestamp),
+        };
+        Self {
+            domains: filter.domains,
+            exclude_domains: filter.exclude_domains,
+            lang: filter.lang,
+            mime: filter.mime,
+            first: filter.first.map(range),
+            last: filter.last.map(range),
+        }
+    }
+}
+
+impl From<Component> for proto::Component {
+    fn from(component: Component) -> Self {
+        Self {
+            score: component.score,
+            rank: component.rank as u64,
+        }
+    }
+}
+
+impl From<Explanation> for proto::Explanation {
+    fn from(explanation: Explanation) -> Self {
+        Self {
+            dense: explanation.dense.map(Into::into),
+            sparse: explanation.sparse.map(Into::into),
+            fused: explanation.fused.map(Into::into),
+            rerank: explanation.rerank.map(Into::into),
+            ltr: explanation.ltr.map(Into::into),
+        }
+    }
+}
+
+impl From<QueryExplanation> for proto::QueryExplanation {
+    fn from(explanation: QueryExplanation) -> Self {
+        Self {
+            text: explanation.text,
+            phrases: explanation.phrases,
+            excluded_terms: explanation.excluded_terms,
+            excluded_phrases: explanation.excluded_phrases,
+            filter: Some(explanation.filter.into()),
+            dense_weight: explanation.dense_weight,
+            sparse_weight: explanation.sparse_weight,
+            rerank_depth: explanation.rerank_depth,
+            max_per_domain: explanation.max_per_domain,
+            mmr_lambda: explanation.mmr_lambda,
+            ltr: explanation.ltr,
+        }
+    }
+}
+
+impl From<Snippet> for proto::Snippet {
+    fn from(snippet: Snippet) -> Self {
+        Self {
+            text: snippet.text,
+            highlights: snippet
+                .highlights
+                .into_iter()
+                .map(|highlight| proto::Highlight {
+                    start: highlight.start as u64,
+                    end: highlight.end as u64,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<MatchResult> for proto::Match {
+    fn from(result: MatchResult) -> Self {
+        Self {
+            id: result.id,
+            title: result.title,
+            url: result.url,
+            score: result.score,
+            snippet: result.snippet.map(Into::into),
+            explanation: result.explanation.map(Into::into),
+        }
+    }
+}
+
+impl From<SearchResponse> for proto::SearchResponse {
+    fn from(response: SearchResponse) -> Self {
+        Self {
+            matches: response.matches.into_iter().map(Into::into).collect(),
+            next_cursor: response.next_cursor,
+            total: response.total,
+            explanation: response.explanation.map(Into::into),
+            search_id: response.search_id,
+        }
+    }
+}
+
+fn page_status(status: database::Status) -> proto::PageStatus {
+    match status {
+        database::Status::Indexed => proto::PageStatus::Indexed,
+        database::Status::Empty => proto::PageStatus::Empty,
+        database::Status::Disallowed => proto::PageStatus::Disallowed,
+        database::Status::Failed => proto::PageStatus::Failed,
+    }
+}
+
+impl From<PageResponse> for proto::Page {
+    fn from(page: PageResponse) -> Self {
+        Self {
+            uuid: page.uuid,
+            url: page.url,
+            first: Some(timestamp(page.first)),
+            last: Some(timestamp(page.last)),
+            sha256: page.sha256,
+            status: page_status(page.status).into(),
+            error: page.error,
+            links: page.links,
+            metadata: Some(proto::Metadata {
+                title: page.metadata.title,
+                lang: page.metadata.lang,
+                mime: page.metadata.mime,
+            }),
+            jobs: page.jobs,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Search for SearchService {
+    async fn search(
+        &self,
+        request: Request<proto::SearchRequest>,
+    ) -> Result<Response<proto::SearchResponse>, Status> {
+        self.check(&request).await?;
+        let session = analytics::session(&request.metadata().clone().into_headers());
+        let request = request.into_inner();
+        let request = SearchRequest {
+            query: request.query,
+            limit: request.limit,
+            offset: request.offset,
+            cursor: request.cursor,
+            dense_weight: request.dense_weight,
+            sparse_weight: request.sparse_weight,
+            rerank: request.rerank,
+            rerank_depth: request.rerank_depth,
+            filter: filter(request.filter)?,
+            max_per_domain: request.max_per_domain,
+            mmr_lambda: request.mmr_lambda,
+            explain: request.explain,
+            ltr: request.ltr,
+        };
+        validate::search(&request).map_err(|fields| status(ApiError::invalid(fields)))?;
+        let (_, response) = crate::search(self.state.clone(), request, session)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(response.into()))
+    }
+
+    async fn similar(
+        &self,
+        request: Request<proto::SimilarRequest>,
+    ) -> Result<Response<proto::SearchResponse>, Status> {
+        self.check(&request).await?;
+        let request = request.into_inner();
+        let uuid = request.uuid;
+        let request = SimilarRequest {
+            limit: request.limit,
+            offset: request.offset,
+            filter: filter(request.filter)?,
+            negative: request.negative,
+        };
+        validate::similar(&request).map_err(|fields| status(ApiError::invalid(fields)))?;
+        let response = crate::similar(self.state.clone(), uuid, request)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(response.into()))
+    }
+
+    async fn get_page(
+        &self,
+        request: Request<proto::GetPageRequest>,
+    ) -> Result<Response<proto::Page>, Status> {
+        self.check(&request).await?;
+        let page = crate::find_page(&self.state, &request.into_inner().uuid)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(page.into()))
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::{value::Kind, ScoredPoint};
+use utils::ltr::Model;
+
+use crate::{explain::Stages, models::point_id};
+
+fn string<'a>(point: &'a ScoredPoint, key: &str) -> Option<&'a str> {
+    match point.payload.get(key)?.kind.as_ref()? {
+        Kind::StringValue(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Share of `words` that are `found`.
+fn share(words: &[String], found: impl Fn(&str) -> bool) -> f32 {
+    if words.is_empty() {
+        return 0.0;
+    }
+    let count = words.iter().filter(|word| found(word)).count();
+    count as f32 / words.len() as f32
+}
+
+/// Ranking features of `point` for a query made of `words`, see
+/// [`utils::ltr::FEATURES`].
+pub fn features(
+    point: &ScoredPoint,
+    id: &str,
+    stages: &Stages,
+    words: &[String],
+    now: DateTime<Utc>,
+) -> Vec<f32> {
+    let dense = stages.dense.get(id).map_or(0.0, |c| c.score);
+    let sparse = stages.sparse.get(id).map_or(0.0, |c| c.score);
+    let freshness = string(point, "last")
+        .and_then(|last| DateTime::parse_from_rfc3339(last).ok())
+        .map_or(0.0, |last| {
+            let days = (now - last.with_timezone(&Utc)).num_hours().max(0) as f32 / 24.0;
+            1.0 / (1.0 + days / 30.0)
+        });
+    let title: HashSet<String> = string(point, "title")
+        .unwrap_or_default()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let title_match = share(words, |word| title.contains(word));
+    let domain = string(point, "domain").unwrap_or_default().to_lowercase();
+    let domain_match = share(words, |word| domain.contains(word));
+    vec![dense, sparse, freshness, title_match, domain_match]
+}
+
+/// Orders the first `depth` points by the score of `model`, which replaces theirs.
+/// Points without features keep their rank below the rescored ones.
+pub fn rescore(
+    model: &Model,
+    points: &mut Vec<ScoredPoint>,
+    features: &HashMap<String, Vec<f32>>,
+    depth: usize,
+) {
+    let rest = points.split_off(depth.min(points.len()));
+    let (mut rescored, unscored): (Vec<_>, Vec<_>) = points
+        .drain(..)
+        .map(|point| {
+            let score = point_id(&point.id)
+                .and_then(|id| features.get(&id))
+                .map(|features| model.score(features));
+            (point, score)
+        })
+        .partition(|(_, score)| score.is_some());
+    rescored.sort_by(|(_, a), (_, b)| b.unwrap_or_default().total_cmp(&a.unwrap_or_default()));
+    points.extend(
+        rescored
+            .into_iter()
+            .map(|(point, score)| ScoredPoint {
+                score: score.unwrap_or_default(),
+                ..point
+            })
+            .chain(unscored.into_iter().map(|(point, _)| point))
+            .chain(rest),
+    );
+}
+
+/// Learned ranking model, reloaded whenever its file changes.
+pub struct Ranker {
+    model: RwLock<Option<Arc<Model>>>,
+    path: Option<PathBuf>,
+    /// Number of top candidates rescored.
+    pub depth: usize,
+    /// Share of sessions ranked by the model, the others keep the fused order.
+    traffic: f32,
+}
+
+impl Ranker {
+    pub fn new(path: Option<PathBuf>, depth: usize, traffic: f32) -> Self {
+        Self {
+            model: RwLock::new(None),
+            path,
+            depth,
+            traffic,
+        }
+    }
+
+    pub fn model(&self) -> Option<Arc<Model>> {
+        self.model.read().unwrap().clone()
+    }
+
+    /// Whether searches keyed by `key`, their session or query, are ranked by the model.
+    ///
+    /// The assignment is stable so that a session stays in the same group of the A/B
+    /// comparison.
+    pub fn assign(&self, key: &str) -> bool {
+        if self.model().is_none() {
+            return false;
+        }
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % 10_000) as f32 / 10_000.0 < self.traffic
+    }
+
+    fn reload(&self, path: &Path, modified: &mut Option<SystemTime>) {
+        let current = match std::fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(current) => current,
+            Err(e) => {
+                tracing::warn!(path = %path.display(), "Failed to read ranking model metadata: {e:#}");
+                return;
+            }
+        };
+        if *modified == Some(current) {
+            return;
+        }
+        match Model::load(path) {
+            Ok(model) => {
+                *self.model.write().unwrap() = Some(Arc::new(model));
+                *modified = Some(current);
+                tracing::info!(path = %path.display(), "Loaded ranking model");
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), "Failed to load ranking model: {e:#}")
+            }
+        }
+    }
+
+    /// Loads the model now and reloads it whenever its file changes, checking every
+    /// `interval`.
+    pub fn spawn_reload(self: Arc<Self>, interval: Duration) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            let mut modified = None;
+            loop {
+                interval.tick().await;
+                self.reload(&path, &mut modified);
+            }
+        });
+    }
+}
@@ -1,119 +1,908 @@
+mod access;
+mod admin;
+mod analytics;
+mod cache;
+mod cursor;
+mod diversify;
+mod explain;
+mod filter;
+mod fusion;
+mod grpc;
+mod ltr;
 mod models;
+mod openapi;
+mod query;
+mod rerank;
+mod snippet;
 mod state;
+mod suggest;
+mod ui;
+mod validate;
 
-use std::net::SocketAddr;
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
-use axum::{extract::State, routing::get, Json, Router};
-use models::{error::ApiError, SearchRequest, SearchResponse};
-use proto::{EmbedRequest, EmbedResponse};
-use qdrant_client::qdrant::{SearchPointsBuilder, SearchResponse as QdrantSearchResponse};
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware,
+    response::Redirect,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use cache::Cache;
+use cursor::Cursor;
+use grpc::proto::search_server::SearchServer;
+use models::{
+    error::{ApiError, FieldError},
+    point_id, query_param, ClickRequest, MatchResult, PageResponse, QueryExplanation, SearchParams,
+    SearchRequest, SearchResponse, SimilarParams, SimilarRequest, SuggestParams, SuggestResponse,
+};
+use mongodb::bson::{doc, Uuid};
+use mongodm::{f, ToRepository};
+use qdrant_client::{
+    qdrant::{
+        Condition, CountPointsBuilder, Filter, GetPointsBuilder, PointId, RecommendPointsBuilder,
+        SearchPointGroupsBuilder, SearchPointsBuilder,
+    },
+    QdrantError,
+};
 use state::{AppConfig, AppState};
-use utils::database::COLLNAME;
-
-mod proto {
-    tonic::include_proto!("tei.v1");
-}
+use tower_http::services::{ServeDir, ServeFile};
+use utils::database::{Click, Page, QueryLog, COLLNAME, DATABASE, SPARSE_VECTOR};
+use utoipa::OpenApi;
+use utoipa_redoc::{Redoc, Servable};
+use utoipa_swagger_ui::SwaggerUi;
 
 #[axum::debug_handler]
 async fn fallback() -> ApiError {
+    not_found("Endpoint not Found".to_string())
+}
+
+/// Weight of each ranking in the fusion when the request does not set it.
+const DEFAULT_WEIGHT: f32 = 1.0;
+
+fn internal_error(message: &str) -> ApiError {
+    ApiError {
+        message: message.to_string(),
+        error: models::error::ErrorType::InternalServerError,
+        fields: Vec::new(),
+    }
+}
+
+fn bad_request(message: String) -> ApiError {
     ApiError {
-        message: "Endpoint not Found".to_string(),
+        message,
+        error: models::error::ErrorType::BadRequest,
+        fields: Vec::new(),
+    }
+}
+
+fn not_found(message: String) -> ApiError {
+    ApiError {
+        message,
         error: models::error::ErrorType::NotFound,
+        fields: Vec::new(),
     }
 }
 
+/// Reports invalid fields under the names of their query-string parameters.
+fn invalid_params(fields: Vec<FieldError>) -> ApiError {
+    let fields = fields
+        .into_iter()
+        .map(|error| FieldError {
+            field: query_param(&error.field),
+            ..error
+        })
+        .collect();
+    ApiError::invalid(fields)
+}
+
+/// Keeps the results that parsed, logging the others.
+fn parse_matches(results: impl Iterator<Item = anyhow::Result<MatchResult>>) -> Vec<MatchResult> {
+    results
+        .filter_map(|result| match result {
+            Ok(result) => Some(result),
+            Err(e) => {
+                tracing::error!("Failed to parse search result: {e:#}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Embeds the free text of a query, through the cache if there is one.
+///
+/// Returns whether the embedding was cached, or `None` without a cache.
+async fn embed_query(state: &AppState, text: &str) -> anyhow::Result<(Vec<f32>, Option<bool>)> {
+    let Some(cache) = &state.cache else {
+        return Ok((state.embedder.embed(text.to_string()).await?, None));
+    };
+    let model_id = state.embedder.model_id();
+    if let Some(embedding) = cache.embedding(model_id, text).await {
+        return Ok((embedding, Some(true)));
+    }
+    let embedding = state.embedder.embed(text.to_string()).await?;
+    cache.set_embedding(model_id, text, &embedding).await;
+    Ok((embedding, Some(false)))
+}
+
+/// Searches pages, with the common parameters in the query string.
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    tag = "search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "Page of results", body = SearchResponse),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 429, description = "Rate limit exceeded", body = ApiError),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
 #[axum::debug_handler]
-#[tracing::instrument(skip(state, query, limit, offset), fields(query = query.len(), limit = limit, offset = offset))]
-async fn search(
+async fn search_get(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    params: Result<Query<SearchParams>, QueryRejection>,
+) -> Result<(HeaderMap, SearchResponse), ApiError> {
+    let Query(params) = params.map_err(|e| bad_request(e.body_text()))?;
+    let request = SearchRequest::from(params);
+    validate::search(&request).map_err(invalid_params)?;
+    search(state, request, analytics::session(&headers)).await
+}
+
+/// Searches pages, with every parameter in a JSON body.
+#[utoipa::path(
+    post,
+    path = "/api/v1/search",
+    tag = "search",
+    request_body = SearchRequest,
+    responses(
+        (status = 200, description = "Page of results", body = SearchResponse),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 429, description = "Rate limit exceeded", body = ApiError),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
+#[axum::debug_handler]
+async fn search_post(
     State(state): State<AppState>,
-    Json(SearchRequest {
+    headers: HeaderMap,
+    request: Result<Json<SearchRequest>, JsonRejection>,
+) -> Result<(HeaderMap, SearchResponse), ApiError> {
+    let Json(request) = request.map_err(|e| bad_request(e.body_text()))?;
+    validate::search(&request).map_err(ApiError::invalid)?;
+    search(state, request, analytics::session(&headers)).await
+}
+
+/// Records a result opened from a search.
+#[utoipa::path(
+    post,
+    path = "/api/v1/click",
+    tag = "analytics",
+    request_body = ClickRequest,
+    responses(
+        (status = 204, description = "Click recorded"),
+        (status = 404, description = "Unknown search or analytics disabled", body = ApiError),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 429, description = "Rate limit exceeded", body = ApiError),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
+#[axum::debug_handler]
+async fn click(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Result<Json<ClickRequest>, JsonRejection>,
+) -> Result<StatusCode, ApiError> {
+    let Json(request) = request.map_err(|e| bad_request(e.body_text()))?;
+    validate::click(&request).map_err(ApiError::invalid)?;
+    let session = analytics::session(&headers);
+    let Some(analytics) = &state.analytics else {
+        return Err(not_found("Analytics are disabled".to_string()));
+    };
+    let search_id = Uuid::parse_str(&request.search_id)
+        .map_err(|_| bad_request(format!("Invalid search id `{}`", request.search_id)))?;
+    let search = analytics
+        .find_query(search_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to find search: {e:#}");
+            internal_error("Failed to record click")
+        })?
+        .ok_or_else(|| not_found(format!("Search `{search_id}` not found")))?;
+    let click = Click {
+        time: chrono::Utc::now(),
+        session: session.or(search.session),
+        search: search_id,
+        query: search.query,
+        page: request.page_id.to_lowercase(),
+        position: request.position as i64,
+    };
+    analytics.log_click(click).await.map_err(|e| {
+        tracing::error!("Failed to record click: {e:#}");
+        internal_error("Failed to record click")
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Serves a validated search, through the results cache if there is one, and logs it.
+#[tracing::instrument(skip_all, fields(query = request.query.len(), limit = request.limit, offset = request.offset))]
+async fn search(
+    state: AppState,
+    mut request: SearchRequest,
+    session: Option<String>,
+) -> Result<(HeaderMap, SearchResponse), ApiError> {
+    tracing::info!(
+        query_length = request.query.len(),
+        limit = request.limit,
+        offset = request.offset,
+        "Search request"
+    );
+    let started = Instant::now();
+    let offset = match request.cursor.as_deref().map(Cursor::decode) {
+        Some(Ok(cursor)) => cursor.offset,
+        _ => request.offset.unwrap_or(0),
+    };
+    // Resolved before the cache lookup, both variants of the A/B comparison are cached
+    // apart.
+    let key = session.as_deref().unwrap_or(&request.query);
+    let ltr = request.ltr.unwrap_or_else(|| state.ranker.assign(key));
+    let ltr = ltr && state.ranker.model().is_some();
+    request.ltr = Some(ltr);
+    let query = request.query.clone();
+    let filter = request.filter.clone();
+    let mut headers = HeaderMap::new();
+    let digest = match &state.cache {
+        Some(cache) => cache.version().await.map(|version| {
+            let request = SearchRequest {
+                query: cache::normalize(&request.query),
+                ..request.clone()
+            };
+            Cache::results_digest(state.embedder.model_id(), version, &request)
+        }),
+        None => None,
+    };
+    let mut cached = None;
+    if let (Some(cache), Some(digest)) = (&state.cache, &digest) {
+        cached = cache.results(digest).await;
+        let status = HeaderValue::from_static(cache::status(cached.is_some()));
+        headers.insert(cache::RESULTS_HEADER, status);
+    }
+
+    let from_cache = cached.is_some();
+    let (mut response, features) = match cached {
+        Some(response) => (response, Vec::new()),
+        None => {
+            let Outcome {
+                response,
+                embedding_cached,
+                features,
+            } = run(&state, request).await?;
+            if let Some(cached) = embedding_cached {
+                let status = HeaderValue::from_static(cache::status(cached));
+                headers.insert(cache::EMBEDDING_HEADER, status);
+            }
+            if let (Some(cache), Some(digest)) = (&state.cache, &digest) {
+                cache.set_results(digest, &response).await;
+            }
+            (response, features)
+        }
+    };
+
+    // Only first pages count, following ones are the same search.
+    if offset == 0 && !response.matches.is_empty() {
+        let suggester = state.suggester.clone();
+        let query = query.clone();
+        tokio::spawn(async move { suggester.record(&query).await });
+    }
+    if let Some(analytics) = &state.analytics {
+        let search_id = Uuid::new();
+        analytics.log_query(QueryLog {
+            uuid: search_id,
+            time: chrono::Utc::now(),
+            session,
+            query,
+            filter: mongodb::bson::to_document(&filter).unwrap_or_default(),
+            offset: offset as i64,
+            results: response.matches.iter().map(|m| m.id.clone()).collect(),
+            latency_ms: started.elapsed().as_millis() as i64,
+            cached: from_cache,
+            features,
+            ltr,
+        });
+        // Set on every response, cached ones included, for clicks to refer to this search.
+        response.search_id = Some(search_id.to_string());
+    }
+    Ok((headers, response))
+}
+
+/// Approximate number of pages matching `filter` and containing a word of the query.
+async fn estimate_total(state: &AppState, filter: &Filter, query: &query::Query) -> Option<u64> {
+    let mut filter = filter.clone();
+    filter.should.extend(
+        query
+            .words()
+            .into_iter()
+            .map(|word| Condition::matches_text("passages", word)),
+    );
+    let count = CountPointsBuilder::new(COLLNAME)
+        .filter(filter)
+        .exact(false);
+    match state.qdrant_client.count(count).await {
+        Ok(response) => response.result.map(|result| result.count),
+        Err(e) => {
+            tracing::warn!("Failed to count results: {e:#}");
+            None
+        }
+    }
+}
+
+/// Response of a search that ran.
+struct Outcome {
+    response: SearchResponse,
+    /// Whether the query embedding was cached, `None` without a cache.
+    embedding_cached: Option<bool>,
+    /// Ranking features of the results, in order, recorded only with analytics.
+    features: Vec<Vec<f32>>,
+}
+
+/// Searches the index.
+async fn run(
+    state: &AppState,
+    SearchRequest {
         query,
         limit,
         offset,
-    }): Json<SearchRequest>,
-) -> Result<SearchResponse, ApiError> {
-    tracing::info!(
-        query_length = query.len(),
-        limit = limit,
-        offset = offset,
-        "Search request"
+        cursor,
+        dense_weight,
+        sparse_weight,
+        rerank,
+        rerank_depth,
+        mut filter,
+        max_per_domain,
+        mmr_lambda,
+        explain,
+        ltr,
+    }: SearchRequest,
+) -> Result<Outcome, ApiError> {
+    let query = query::parse(&query, &mut filter).map_err(|e| bad_request(format!("{e:#}")))?;
+    let limit = limit.unwrap_or(validate::DEFAULT_LIMIT);
+    let cursor = cursor
+        .as_deref()
+        .map(Cursor::decode)
+        .transpose()
+        .map_err(|e| bad_request(format!("{e:#}")))?;
+    let offset = match &cursor {
+        Some(cursor) => cursor.offset,
+        None => offset.unwrap_or(0),
+    };
+    let dense_weight = dense_weight.unwrap_or(DEFAULT_WEIGHT);
+    let sparse_weight = sparse_weight.unwrap_or(DEFAULT_WEIGHT);
+    let reranker = match (rerank.unwrap_or(false), &state.reranker) {
+        (true, Some(reranker)) => Some(reranker.clone()),
+        (true, None) => {
+            tracing::warn!("Reranking requested without a configured reranker");
+            None
+        }
+        (false, _) => None,
+    };
+    let depth = rerank_depth.unwrap_or(rerank::DEFAULT_DEPTH);
+    let model = ltr.unwrap_or(false).then(|| state.ranker.model()).flatten();
+    // Results keep their fused order unless they are reordered, which the cursor of
+    // the next page relies on when its last result is no longer ranked.
+    let by_score = reranker.is_none() && mmr_lambda.is_none() && model.is_none();
+    // Both rankings have to cover the requested page, plus one result telling whether
+    // there is a next one, and the reranked candidates for the fused one to. With a
+    // cursor too, as fused scores do not translate to a threshold of either search.
+    let mut window = offset + limit + 1;
+    if reranker.is_some() {
+        window = window.max(depth);
+    }
+    if model.is_some() {
+        window = window.max(state.ranker.depth as u64);
+    }
+    // MMR compares the dense embeddings of the candidates.
+    let with_vectors = mmr_lambda.is_some();
+    let explanation = explain.unwrap_or(false).then(|| QueryExplanation {
+        text: query.text.clone(),
+        phrases: query.phrases.clone(),
+        excluded_terms: query.excluded_terms.clone(),
+        excluded_phrases: query.excluded_phrases.clone(),
+        filter: filter.clone(),
+        dense_weight,
+        sparse_weight,
+        rerank_depth: reranker.is_some().then_some(depth),
+        max_per_domain,
+        mmr_lambda,
+        ltr: model.is_some(),
+    });
+    // Ranking features are computed from the stages, for the model and the query log.
+    let with_features = model.is_some() || state.analytics.is_some();
+    let mut stages = (explanation.is_some() || with_features).then(explain::Stages::default);
+
+    let (embeddings, sparse) = tokio::join!(
+        embed_query(state, &query.text),
+        state.sparse_embedder.embed_query(query.text.clone())
+    );
+    let (embeddings, embedding_cached) = embeddings.map_err(|e| {
+        tracing::error!("Failed to embed query: {e:#}");
+        internal_error("Failed to process query")
+    })?;
+    let sparse = sparse.map_err(|e| {
+        tracing::error!("Failed to embed sparse query: {e:#}");
+        internal_error("Failed to process query")
+    })?;
+
+    let mut filter = filter.to_qdrant();
+    let (must, must_not) = query.conditions();
+    filter.must.extend(must);
+    filter.must_not.extend(must_not);
+    let dense_search = async {
+        if dense_weight == 0.0 {
+            return Ok::<_, QdrantError>(Vec::new());
+        }
+        match max_per_domain {
+            Some(max) => {
+                let search_groups = SearchPointGroupsBuilder::new(
+                    COLLNAME,
+                    embeddings,
+                    window as u32,
+                    "domain",
+                    max,
+                )
+                .filter(filter.clone())
+                .with_payload(true)
+                .with_vectors(with_vectors);
+                let response = state.qdrant_client.search_groups(search_groups).await?;
+                Ok(diversify::flatten(
+                    response.result.map(|r| r.groups).unwrap_or_default(),
+                ))
+            }
+            None => {
+                let search_points = SearchPointsBuilder::new(COLLNAME, embeddings, window)
+                    .filter(filter.clone())
+                    .with_payload(true)
+                    .with_vectors(with_vectors);
+                let response = state.qdrant_client.search_points(search_points).await?;
+                Ok(response.result)
+            }
+        }
+    };
+    let sparse_search = async {
+        if sparse_weight == 0.0 || sparse.is_empty() {
+            return Ok::<_, QdrantError>(Vec::new());
+        }
+        match max_per_domain {
+            Some(max) => {
+                let search_groups = SearchPointGroupsBuilder::new(
+                    COLLNAME,
+                    sparse.values,
+                    window as u32,
+                    "domain",
+                    max,
+                )
+                .sparse_indices(sparse.indices)
+                .vector_name(SPARSE_VECTOR)
+                .filter(filter.clone())
+                .with_payload(true)
+                .with_vectors(with_vectors);
+                let response = state.qdrant_client.search_groups(search_groups).await?;
+                Ok(diversify::flatten(
+                    response.result.map(|r| r.groups).unwrap_or_default(),
+                ))
+            }
+            None => {
+                let search_points = SearchPointsBuilder::new(COLLNAME, sparse.values, window)
+                    .sparse_indices(sparse.indices)
+                    .vector_name(SPARSE_VECTOR)
+                    .filter(filter.clone())
+                    .with_payload(true)
+                    .with_vectors(with_vectors);
+                let response = state.qdrant_client.search_points(search_points).await?;
+                Ok(response.result)
+            }
+        }
+    };
+    let (dense, sparse, total) = tokio::join!(
+        dense_search,
+        sparse_search,
+        estimate_total(state, &filter, &query)
     );
-    let EmbedResponse { embeddings, .. } = state
-        .tei_client
-        .clone()
-        .embed(EmbedRequest {
-            inputs: query,
-            truncate: true,
-            truncation_direction: 0,
-            prompt_name: None,
-            normalize: true,
+    let (dense, sparse) = dense.and_then(|dense| Ok((dense, sparse?))).map_err(|e| {
+        tracing::error!("Failed to search points: {e:#}");
+        internal_error("Failed to find results")
+    })?;
+
+    if let Some(stages) = &mut stages {
+        stages.dense = explain::ranks(&dense);
+        stages.sparse = explain::ranks(&sparse);
+    }
+    let mut result = fusion::rrf(vec![(dense_weight, dense), (sparse_weight, sparse)]);
+    result.retain(|point| query.accepts(&point.payload));
+    if let Some(max) = max_per_domain {
+        diversify::cap_per_domain(&mut result, max);
+    }
+    if let Some(stages) = &mut stages {
+        stages.fused = explain::ranks(&result);
+    }
+    let mut features = HashMap::new();
+    if let (true, Some(stages)) = (with_features, &stages) {
+        let words = query.words();
+        let now = chrono::Utc::now();
+        for point in &result {
+            if let Some(id) = point_id(&point.id) {
+                let values = ltr::features(point, &id, stages, &words, now);
+                features.insert(id, values);
+            }
+        }
+    }
+    if let Some(model) = &model {
+        ltr::rescore(model, &mut result, &features, state.ranker.depth);
+        if let Some(stages) = &mut stages {
+            stages.ltr = explain::ranks(&result[..state.ranker.depth.min(result.len())]);
+        }
+    }
+    if let Some(reranker) = reranker {
+        let rest = result.split_off((depth as usize).min(result.len()));
+        result = rerank::rerank(reranker, query.text.clone(), result)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to rerank results: {e:#}");
+                internal_error("Failed to rank results")
+            })?;
+        if let Some(stages) = &mut stages {
+            stages.rerank = explain::ranks(&result);
+        }
+        result.extend(rest);
+    }
+    if let Some(lambda) = mmr_lambda {
+        // Only the results up to the requested page are reordered, off the executor as
+        // it compares their embeddings to every candidate.
+        let count = (offset + limit + 1) as usize;
+        result = tokio::task::spawn_blocking(move || diversify::mmr(result, lambda, count))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to diversify results: {e:#}");
+                internal_error("Failed to rank results")
+            })?;
+    }
+    let start = match &cursor {
+        Some(cursor) => cursor.start(&result, by_score),
+        None => (offset as usize).min(result.len()),
+    };
+    let end = (start + limit as usize).min(result.len());
+    let next_cursor = if result.len() > end && end > start {
+        Cursor::after(&result[end - 1], end as u64).map(|cursor| cursor.encode())
+    } else {
+        None
+    };
+    let terms = snippet::Terms::new(&query.text);
+    let matches = parse_matches(result.into_iter().skip(start).take(end - start).map(|s| {
+        let snippet = snippet::best(&terms, &s.payload);
+        MatchResult::try_from(s).map(|result| MatchResult {
+            snippet,
+            explanation: stages
+                .as_ref()
+                .filter(|_| explanation.is_some())
+                .map(|stages| stages.explain(&result.id)),
+            ..result
         })
+    }));
+    let features = match &state.analytics {
+        Some(_) => matches
+            .iter()
+            .map(|result| features.remove(&result.id).unwrap_or_default())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let response = SearchResponse {
+        matches,
+        next_cursor,
+        total,
+        explanation,
+        search_id: None,
+    };
+    Ok(Outcome {
+        response,
+        embedding_cached,
+        features,
+    })
+}
+
+/// Crawl record of a page.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{uuid}",
+    tag = "pages",
+    params(("uuid" = String, Path, description = "UUID of the page")),
+    responses(
+        (status = 200, description = "Page", body = PageResponse),
+        (status = 404, description = "Unknown page", body = ApiError),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 429, description = "Rate limit exceeded", body = ApiError),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
+#[axum::debug_handler]
+#[tracing::instrument(skip(state))]
+async fn page(
+    State(state): State<AppState>,
+    Path(uuid): Path<String>,
+) -> Result<PageResponse, ApiError> {
+    find_page(&state, &uuid).await
+}
+
+/// Crawl record of the page `uuid`, shared by the REST and gRPC APIs.
+async fn find_page(state: &AppState, uuid: &str) -> Result<PageResponse, ApiError> {
+    let uuid =
+        Uuid::parse_str(uuid).map_err(|_| bad_request(format!("Invalid page id `{uuid}`")))?;
+    let page = state
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Page>()
+        .find_one(doc! { f!(uuid in Page): uuid })
         .await
         .map_err(|e| {
-            tracing::error!("Failed to embed query: {e:#}");
-            ApiError {
-                message: "Failed to process query".to_string(),
-                error: models::error::ErrorType::InternalServerError,
-            }
+            tracing::error!("Failed to find page: {e:#}");
+            internal_error("Failed to find page")
+        })?
+        .ok_or_else(|| not_found(format!("Page `{uuid}` not found")))?;
+    Ok(page.into())
+}
+
+/// Pages similar to a page, with the parameters in the query string.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pages/{uuid}/similar",
+    tag = "pages",
+    params(("uuid" = String, Path, description = "UUID of the page"), SimilarParams),
+    responses(
+        (status = 200, description = "Page of results", body = SearchResponse),
+        (status = 404, description = "Unknown page", body = ApiError),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 429, description = "Rate limit exceeded", body = ApiError),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
+#[axum::debug_handler]
+async fn similar_get(
+    State(state): State<AppState>,
+    Path(uuid): Path<String>,
+    params: Result<Query<SimilarParams>, QueryRejection>,
+) -> Result<SearchResponse, ApiError> {
+    let Query(params) = params.map_err(|e| bad_request(e.body_text()))?;
+    let request = SimilarRequest::from(params);
+    validate::similar(&request).map_err(invalid_params)?;
+    similar(state, uuid, request).await
+}
+
+/// Pages similar to a page, with the parameters in a JSON body.
+#[utoipa::path(
+    post,
+    path = "/api/v1/pages/{uuid}/similar",
+    tag = "pages",
+    params(("uuid" = String, Path, description = "UUID of the page")),
+    request_body = SimilarRequest,
+    responses(
+        (status = 200, description = "Page of results", body = SearchResponse),
+        (status = 404, description = "Unknown page", body = ApiError),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 429, description = "Rate limit exceeded", body = ApiError),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
+#[axum::debug_handler]
+async fn similar_post(
+    State(state): State<AppState>,
+    Path(uuid): Path<String>,
+    request: Result<Json<SimilarRequest>, JsonRejection>,
+) -> Result<SearchResponse, ApiError> {
+    let Json(request) = request.map_err(|e| bad_request(e.body_text()))?;
+    validate::similar(&request).map_err(ApiError::invalid)?;
+    similar(state, uuid, request).await
+}
+
+/// Recommends pages like a validated request's page.
+#[tracing::instrument(skip(state, limit, offset, filter, negative))]
+async fn similar(
+    state: AppState,
+    uuid: String,
+    SimilarRequest {
+        limit,
+        offset,
+        filter,
+        negative,
+    }: SimilarRequest,
+) -> Result<SearchResponse, ApiError> {
+    let parse = |id: &String| {
+        Uuid::parse_str(id)
+            .map(|uuid| uuid.to_string())
+            .map_err(|_| bad_request(format!("Invalid page id `{id}`")))
+    };
+    let uuid = parse(&uuid)?;
+    let negative = negative.iter().map(parse).collect::<Result<Vec<_>, _>>()?;
+
+    let ids: Vec<PointId> = std::iter::once(&uuid)
+        .chain(&negative)
+        .map(|id| PointId::from(id.clone()))
+        .collect();
+    let known: HashSet<_> = state
+        .qdrant_client
+        .get_points(GetPointsBuilder::new(COLLNAME, ids).with_payload(false))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get points: {e:#}");
+            internal_error("Failed to find page")
         })?
-        .into_inner();
-    let search_points = SearchPointsBuilder::new(COLLNAME, embeddings, limit.unwrap_or(10).min(50))
-        .offset(offset.unwrap_or(0))
-        .with_payload(true);
-    let QdrantSearchResponse { result, .. } = state
+        .result
+        .into_iter()
+        .filter_map(|point| point_id(&point.id))
+        .collect();
+    if !known.contains(&uuid) {
+        return Err(not_found(format!("Page `{uuid}` not found")));
+    }
+    if let Some(id) = negative.iter().find(|id| !known.contains(*id)) {
+        return Err(bad_request(format!("Negative page `{id}` not found")));
+    }
+
+    let mut recommend =
+        RecommendPointsBuilder::new(COLLNAME, limit.unwrap_or(validate::DEFAULT_LIMIT))
+            .add_positive(PointId::from(uuid))
+            .filter(filter.to_qdrant())
+            .offset(offset.unwrap_or(0))
+            .with_payload(true);
+    for id in negative {
+        recommend = recommend.add_negative(PointId::from(id));
+    }
+    let response = state
         .qdrant_client
-        .search_points(search_points)
+        .recommend(recommend)
         .await
         .map_err(|e| {
-            tracing::error!("Failed to search points: {e:#}");
-            ApiError {
-                message: "Failed to find results".to_string(),
-                error: models::error::ErrorType::InternalServerError,
-            }
+            tracing::error!("Failed to recommend points: {e:#}");
+            internal_error("Failed to find results")
         })?;
 
-    let matches: Vec<_> = result
-        .into_iter()
-        .map(|s| s.payload.try_into())
-        .filter_map(|result| match result {
-            Ok(result) => Some(result),
-            Err(e) => {
-                tracing::error!("Failed to parse search result: {e:#}");
-                None
-            }
-        })
-        .collect();
+    let matches = parse_matches(response.result.into_iter().map(MatchResult::try_from));
+    Ok(SearchResponse {
+        matches,
+        next_cursor: None,
+        total: None,
+        explanation: None,
+        search_id: None,
+    })
+}
 
-    Ok(SearchResponse { matches })
+/// Completions of a query prefix, most popular first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/suggest",
+    tag = "search",
+    params(SuggestParams),
+    responses(
+        (status = 200, description = "Suggestions", body = SuggestResponse),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Missing or invalid API key", body = ApiError),
+        (status = 429, description = "Rate limit exceeded", body = ApiError),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
+#[axum::debug_handler]
+async fn suggest(
+    State(state): State<AppState>,
+    params: Result<Query<SuggestParams>, QueryRejection>,
+) -> Result<SuggestResponse, ApiError> {
+    let Query(params) = params.map_err(|e| bad_request(e.body_text()))?;
+    validate::suggest(&params).map_err(ApiError::invalid)?;
+    let limit = params.limit.unwrap_or(suggest::DEFAULT_LIMIT);
+    let suggestions = state.suggester.suggest(&params.q, limit);
+    Ok(SuggestResponse { suggestions })
 }
 
 async fn serve() {
     let app_config = AppConfig::new();
+    let suggest_rebuild = Duration::from_secs(app_config.suggest_rebuild_secs);
+    let ltr_reload = Duration::from_secs(app_config.ltr_reload_secs);
+    let frontend_dir = app_config.frontend_dir.clone();
     let state = state::AppState::new(app_config).await;
+    state.suggester.clone().spawn_rebuild(suggest_rebuild);
+    state.ranker.clone().spawn_reload(ltr_reload);
 
     let socket_address: SocketAddr = "0.0.0.0:8000".parse().unwrap();
     let listener = tokio::net::TcpListener::bind(socket_address).await.unwrap();
 
-    let app = Router::new()
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_serving::<SearchServer<grpc::SearchService>>()
+        .await;
+    let grpc_address: SocketAddr = "0.0.0.0:50051".parse().unwrap();
+    let search_server = SearchServer::new(grpc::SearchService {
+        state: state.clone(),
+    });
+
+    let api = Router::new()
         .nest(
-            "/api",
-            Router::new().nest(
-                "/v1",
-                Router::new()
-                    .route("/search", get(search))
-                    .with_state(state),
-            ),
+            "/v1",
+            Router::new()
+                .route("/search", get(search_get).post(search_post))
+                .route("/suggest", get(suggest))
+                .route("/click", post(click))
+                .route("/pages/:uuid", get(page))
+                .route("/pages/:uuid/similar", get(similar_get).post(similar_post))
+                .nest(
+                    "/admin",
+                    Router::new()
+                        .route("/seeds", post(admin::seed))
+                        .route("/domains", get(admin::domains))
+                        .route("/domains/:domain/pause", post(admin::pause))
+                        .route("/domains/:domain/resume", post(admin::resume))
+                        .route("/recrawl", post(admin::recrawl))
+                        .route("/pages", delete(admin::delete))
+                        .layer(middleware::from_fn(access::admin)),
+                )
+                .layer(middleware::from_fn_with_state(state.clone(), access::limit))
+                .with_state(state.clone()),
         )
+        // Unknown API routes get a JSON error rather than the frontend.
         .fallback(fallback);
+    let search_page = Router::new()
+        .route("/search", get(ui::search))
+        .layer(middleware::from_fn_with_state(state.clone(), access::limit))
+        .with_state(state);
+
+    let app = Router::new()
+        .nest("/api", api)
+        .merge(search_page)
+        .merge(
+            SwaggerUi::new("/api/v1/docs").url("/api/v1/openapi.json", openapi::ApiDoc::openapi()),
+        )
+        .merge(Redoc::with_url("/api/v1/redoc", openapi::ApiDoc::openapi()));
+    let index = frontend_dir.join("index.html");
+    let app = if index.is_file() {
+        tracing::info!("Serving the frontend from {}", frontend_dir.display());
+        // Paths of the single-page app are not files, they all get its index.
+        app.fallback_service(ServeDir::new(&frontend_dir).fallback(ServeFile::new(index)))
+    } else {
+        app.route("/", get(|| async { Redirect::to("/search") }))
+            .fallback(fallback)
+    };
 
-    tracing::info!("Listening on {socket_address}");
+    tracing::info!("Listening on {socket_address}, serving gRPC at {grpc_address}");
 
-    axum::serve(listener, app.into_make_service())
-        .await
-        .unwrap()
+    let rest = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    );
+    let grpc = tonic::transport::Server::builder()
+        .add_service(health_service)
+        .add_service(search_server)
+        .serve(grpc_address);
+    let (rest, grpc) = tokio::join!(rest, grpc);
+    rest.unwrap();
+    grpc.unwrap();
 }
 
 #[tokio::main]
 async fn main() {
+    // Prints the OpenAPI document, for clients to generate their types from.
+    if std::env::args().nth(1).as_deref() == Some("openapi") {
+        println!("{}", openapi::ApiDoc::openapi().to_pretty_json().unwrap());
+        return;
+    }
     utils::start(env!("CARGO_PKG_NAME"), Box::pin(serve())).await;
 }
@@ -1,26 +1,309 @@
-use std::collections::HashMap;
-
+use anyhow::Context;
 use axum::{response::IntoResponse, Json};
-use qdrant_client::qdrant::{value::Kind, Value};
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::{point_id::PointIdOptions, value::Kind, PointId, ScoredPoint, Value};
 use serde::{Deserialize, Serialize};
+use utils::database::{Metadata, Page, Status};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct SearchRequest {
+    #[serde(alias = "q")]
     pub query: String,
     pub limit: Option<u64>,
     pub offset: Option<u64>,
+    /// `next_cursor` of the previous page, instead of `offset`.
+    pub cursor: Option<String>,
+    /// Weight of the semantic ranking in the fusion, skipped if 0.
+    pub dense_weight: Option<f32>,
+    /// Weight of the exact-term ranking in the fusion, skipped if 0.
+    pub sparse_weight: Option<f32>,
+    /// Reorders the top candidates with the cross-encoder, if one is configured.
+    pub rerank: Option<bool>,
+    /// Number of top candidates reranked.
+    pub rerank_depth: Option<u64>,
+    #[serde(default)]
+    pub filter: SearchFilter,
+    /// Maximum number of results from the same domain.
+    pub max_per_domain: Option<u32>,
+    /// Reorders the results by maximal marginal relevance, trading relevance (1) for
+    /// diversity (0).
+    pub mmr_lambda: Option<f32>,
+    /// Explains how the query was interpreted and every result ranked.
+    pub explain: Option<bool>,
+    /// Reorders the top candidates with the learned ranking model, if one is loaded,
+    /// instead of the A/B assignment of the session.
+    pub ltr: Option<bool>,
+}
+
+/// Query-string parameters of the filter of a request, lists are comma-separated.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FilterParams {
+    pub domains: Option<String>,
+    pub exclude_domains: Option<String>,
+    pub lang: Option<String>,
+    pub mime: Option<String>,
+    pub first_from: Option<DateTime<Utc>>,
+    pub first_to: Option<DateTime<Utc>>,
+    pub last_from: Option<DateTime<Utc>>,
+    pub last_to: Option<DateTime<Utc>>,
+}
+
+fn list(value: Option<String>) -> Vec<String> {
+    value
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl From<FilterParams> for SearchFilter {
+    fn from(params: FilterParams) -> Self {
+        let range = |from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>| {
+            (from.is_some() || to.is_some()).then_some(DateRange { from, to })
+        };
+        Self {
+            domains: list(params.domains),
+            exclude_domains: list(params.exclude_domains),
+            lang: list(params.lang),
+            mime: list(params.mime),
+            first: range(params.first_from, params.first_to),
+            last: range(params.last_from, params.last_to),
+        }
+    }
+}
+
+/// Name of the query-string parameter holding a field of a JSON request, e.g.
+/// `first_from` for `filter.first.from`.
+pub fn query_param(field: &str) -> String {
+    match field {
+        "query" => "q".to_string(),
+        field => field
+            .strip_prefix("filter.")
+            .unwrap_or(field)
+            .replace('.', "_"),
+    }
+}
+
+/// Query-string parameters of a search, the subset of [`SearchRequest`] common to
+/// simple clients.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchParams {
+    #[serde(default)]
+    pub q: String,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    pub cursor: Option<String>,
+    pub explain: Option<bool>,
+    #[serde(flatten)]
+    pub filter: FilterParams,
+}
+
+impl From<SearchParams> for SearchRequest {
+    fn from(params: SearchParams) -> Self {
+        Self {
+            query: params.q,
+            limit: params.limit,
+            offset: params.offset,
+            cursor: params.cursor,
+            dense_weight: None,
+            sparse_weight: None,
+            rerank: None,
+            rerank_depth: None,
+            filter: params.filter.into(),
+            max_per_domain: None,
+            mmr_lambda: None,
+            explain: params.explain,
+            ltr: None,
+        }
+    }
+}
+
+/// Result opened from a search.
+#[derive(Deserialize, ToSchema)]
+pub struct ClickRequest {
+    /// `search_id` of the search response.
+    pub search_id: String,
+    /// `id` of the result.
+    pub page_id: String,
+    /// Position of the result in the search, starting at 0.
+    pub position: u64,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestParams {
+    /// Prefix typed so far.
+    #[serde(default)]
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SuggestResponse {
+    pub suggestions: Vec<String>,
+}
+
+impl IntoResponse for SuggestResponse {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SimilarRequest {
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    #[serde(default)]
+    pub filter: SearchFilter,
+    /// UUIDs of pages the results should not be like.
+    #[serde(default)]
+    pub negative: Vec<String>,
+}
+
+/// Query-string parameters of [`SimilarRequest`].
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SimilarParams {
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    /// Comma-separated UUIDs.
+    pub negative: Option<String>,
+    #[serde(flatten)]
+    pub filter: FilterParams,
+}
+
+impl From<SimilarParams> for SimilarRequest {
+    fn from(params: SimilarParams) -> Self {
+        Self {
+            limit: params.limit,
+            offset: params.offset,
+            filter: params.filter.into(),
+            negative: list(params.negative),
+        }
+    }
+}
+
+/// Inclusive range of timestamps, open-ended on the missing side.
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct DateRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Criteria every result must meet, lists match any of their values.
+#[derive(Serialize, Deserialize, Clone, Default, ToSchema)]
+#[serde(default)]
+pub struct SearchFilter {
+    pub domains: Vec<String>,
+    pub exclude_domains: Vec<String>,
+    /// Primary language subtags, e.g. `en`.
+    pub lang: Vec<String>,
+    /// Content types, e.g. `text/html` or `application/pdf`.
+    pub mime: Vec<String>,
+    /// When the page was first crawled.
+    pub first: Option<DateRange>,
+    /// When the page was last crawled.
+    pub last: Option<DateRange>,
+}
+
+/// Range of a snippet matching a query term, in characters.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Highlight {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<Highlight>,
+}
+
+/// Identifier of a point, the UUID of its page.
+pub fn point_id(id: &Option<PointId>) -> Option<String> {
+    match id.as_ref()?.point_id_options.as_ref()? {
+        PointIdOptions::Num(num) => Some(num.to_string()),
+        PointIdOptions::Uuid(uuid) => Some(uuid.clone()),
+    }
+}
+
+/// Score of a result in a ranking, with its position starting at 1.
+#[derive(Serialize, Deserialize, Clone, Copy, ToSchema)]
+pub struct Component {
+    pub score: f32,
+    pub rank: usize,
 }
 
-#[derive(Serialize)]
+/// Rankings a result went through, absent from those it was not part of.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Explanation {
+    /// Cosine similarity of the embeddings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dense: Option<Component>,
+    /// Sparse term vector score.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparse: Option<Component>,
+    /// Weighted reciprocal rank fusion of the dense and sparse rankings, once phrases
+    /// and the domain cap are applied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fused: Option<Component>,
+    /// Cross-encoder score.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<Component>,
+    /// Learned ranking model score.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ltr: Option<Component>,
+}
+
+/// How a query was interpreted, the filters and ranking stages that applied.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct QueryExplanation {
+    /// Free text left once operators are removed, which is embedded.
+    pub text: String,
+    pub phrases: Vec<String>,
+    pub excluded_terms: Vec<String>,
+    pub excluded_phrases: Vec<String>,
+    /// Filter of the request combined with those of the query operators.
+    pub filter: SearchFilter,
+    pub dense_weight: f32,
+    pub sparse_weight: f32,
+    /// Number of top candidates reranked, absent without reranking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank_depth: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_per_domain: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mmr_lambda: Option<f32>,
+    /// Whether the top candidates were reordered by the learned ranking model.
+    pub ltr: bool,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct MatchResult {
+    /// UUID of the page.
+    pub id: String,
     pub title: String,
     pub url: String,
+    /// Score of the last ranking the result went through.
+    pub score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Explanation>,
 }
 
-impl TryFrom<HashMap<String, Value>> for MatchResult {
+impl TryFrom<ScoredPoint> for MatchResult {
     type Error = anyhow::Error;
 
-    fn try_from(mut value: HashMap<String, Value>) -> Result<Self, Self::Error> {
+    fn try_from(point: ScoredPoint) -> Result<Self, Self::Error> {
+        let id = point_id(&point.id).context("Missing point id")?;
+        let score = point.score;
+        let mut value = point.payload;
         macro_rules! get {
             ($key:expr) => {
                 match value.remove($key) {
@@ -35,13 +318,31 @@ impl TryFrom<HashMap<String, Value>> for MatchResult {
         let title = get!("title");
         let url = get!("url");
 
-        Ok(MatchResult { title, url })
+        Ok(MatchResult {
+            id,
+            title,
+            url,
+            score,
+            snippet: None,
+            explanation: None,
+        })
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct SearchResponse {
     pub matches: Vec<MatchResult>,
+    /// Opaque cursor of the next page, absent on the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Approximate number of pages matching the query.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<QueryExplanation>,
+    /// Id of the logged search, which clicks refer to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_id: Option<String>,
 }
 
 impl IntoResponse for SearchResponse {
@@ -50,26 +351,169 @@ impl IntoResponse for SearchResponse {
     }
 }
 
+/// Crawl metadata of a page.
+#[derive(Serialize, ToSchema)]
+pub struct PageResponse {
+    pub uuid: String,
+    pub url: String,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+    /// SHA-256 digest of the last crawled content.
+    pub sha256: String,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub links: i64,
+    pub metadata: Metadata,
+    /// UUIDs of the crawl jobs that discovered the page.
+    pub jobs: Vec<String>,
+}
+
+impl From<Page> for PageResponse {
+    fn from(page: Page) -> Self {
+        Self {
+            uuid: page.uuid.to_string(),
+            url: page.url,
+            first: page.first,
+            last: page.last,
+            sha256: page.sha256,
+            status: page.status,
+            error: page.error,
+            links: page.links,
+            metadata: page.metadata,
+            jobs: page.jobs.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+impl IntoResponse for PageResponse {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// URLs to crawl, each indexed before the response.
+#[derive(Deserialize, ToSchema)]
+pub struct SeedRequest {
+    pub urls: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RejectedUrl {
+    pub url: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SeedResponse {
+    /// Number of URLs crawled.
+    pub crawled: usize,
+    /// URLs the crawler failed to crawl.
+    pub rejected: Vec<RejectedUrl>,
+}
+
+impl IntoResponse for SeedResponse {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+/// Crawl queue of a domain.
+#[derive(Serialize, ToSchema)]
+pub struct DomainQueue {
+    pub domain: String,
+    /// Number of URLs waiting in the queue.
+    pub depth: u64,
+    /// Whether the selector skips the queue.
+    pub paused: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DomainsResponse {
+    /// Queues, deepest first, and paused domains without one.
+    pub domains: Vec<DomainQueue>,
+}
+
+impl IntoResponse for DomainsResponse {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RecrawlRequest {
+    pub url: String,
+}
+
+/// Pages to delete, by exact URL or URL prefix.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteParams {
+    pub url: Option<String>,
+    pub prefix: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DeleteResponse {
+    /// Number of pages deleted.
+    pub deleted: u64,
+}
+
+impl IntoResponse for DeleteResponse {
+    fn into_response(self) -> axum::response::Response {
+        Json(self).into_response()
+    }
+}
+
 pub mod error {
-    use axum::{http::StatusCode, response::IntoResponse};
+    use axum::{http::StatusCode, response::IntoResponse, Json};
     use serde::Serialize;
+    use utoipa::ToSchema;
 
-    #[derive(Serialize)]
+    #[derive(Serialize, ToSchema)]
     pub struct ApiError {
         pub message: String,
         pub error: ErrorType,
+        /// Invalid fields of the request.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub fields: Vec<FieldError>,
+    }
+
+    impl ApiError {
+        pub fn invalid(fields: Vec<FieldError>) -> Self {
+            Self {
+                message: "Invalid request".to_string(),
+                error: ErrorType::BadRequest,
+                fields,
+            }
+        }
+    }
+
+    #[derive(Serialize, ToSchema)]
+    pub struct FieldError {
+        /// Path of the field, e.g. `filter.first.from`.
+        pub field: String,
+        pub message: String,
     }
 
-    #[derive(Serialize)]
+    #[derive(Serialize, ToSchema)]
     pub enum ErrorType {
+        BadRequest,
+        Unauthorized,
+        Forbidden,
         NotFound,
+        TooManyRequests,
         InternalServerError,
     }
 
     impl ErrorType {
         pub fn status_code(&self) -> StatusCode {
             match self {
+                ErrorType::BadRequest => StatusCode::BAD_REQUEST,
+                ErrorType::Unauthorized => StatusCode::UNAUTHORIZED,
+                ErrorType::Forbidden => StatusCode::FORBIDDEN,
                 ErrorType::NotFound => StatusCode::NOT_FOUND,
+                ErrorType::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
                 ErrorType::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             }
         }
@@ -77,7 +521,7 @@ pub mod error {
 
     impl IntoResponse for ApiError {
         fn into_response(self) -> axum::response::Response {
-            (self.error.status_code(), self).into_response()
+            (self.error.status_code(), Json(self)).into_response()
         }
     }
 }
@@ -0,0 +1,50 @@
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::access::API_KEY_HEADER;
+
+/// OpenAPI document of the REST API, generated from the handlers and the types of
+/// [`crate::models`].
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "QueryVerse", description = "Semantic search over crawled pages."),
+    paths(
+        crate::search_get,
+        crate::search_post,
+        crate::suggest,
+        crate::click,
+        crate::page,
+        crate::similar_get,
+        crate::similar_post,
+        crate::admin::seed,
+        crate::admin::domains,
+        crate::admin::pause,
+        crate::admin::resume,
+        crate::admin::recrawl,
+        crate::admin::delete,
+    ),
+    modifiers(&Security),
+    security((), ("api_key" = [])),
+    tags(
+        (name = "search", description = "Search and query suggestions"),
+        (name = "pages", description = "Crawled pages and pages like them"),
+        (name = "analytics", description = "Feedback on search results"),
+        (name = "admin", description = "Crawl management, for admin API keys"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Declares the `x-api-key` header, optional unless the backend requires it.
+struct Security;
+
+impl Modify for Security {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveTime};
+use qdrant_client::qdrant::{value::Kind, Condition, Value};
+
+use crate::models::{DateRange, SearchFilter};
+
+/// Free text of a query and the constraints its operators put on the text of results.
+///
+/// Supported operators:
+/// - `site:docs.rs` and `-site:docs.rs` include or exclude a domain
+/// - `lang:en` restricts results to a language
+/// - `before:2024-01-01` and `after:2024-01-01` bound when pages were first seen
+/// - `"exact phrase"` requires the phrase, which is also part of the free text
+/// - `-term` and `-"exact phrase"` exclude results containing them
+#[derive(Debug, Default)]
+pub struct Query {
+    /// Text left once operators are removed, which is embedded.
+    pub text: String,
+    pub phrases: Vec<String>,
+    pub excluded_terms: Vec<String>,
+    pub excluded_phrases: Vec<String>,
+}
+
+fn date(operator: &str, value: &str) -> anyhow::Result<chrono::DateTime<chrono::Utc>> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        anyhow::anyhow!("Invalid date `{value}` of `{operator}:`, expected YYYY-MM-DD")
+    })?;
+    Ok(date.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// Lowercased alphanumeric words of `text` separated by single spaces, for phrase
+/// comparisons.
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Splits the operators out of `input`, adding those that map onto payload filters to
+/// `filter`.
+pub fn parse(input: &str, filter: &mut SearchFilter) -> anyhow::Result<Query> {
+    let mut query = Query::default();
+    let mut text = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let negated = chars.next_if_eq(&'-').is_some();
+
+        if chars.next_if_eq(&'"').is_some() {
+            let mut phrase = String::new();
+            let mut closed = false;
+            for c in chars.by_ref() {
+                if c == '"' {
+                    closed = true;
+                    break;
+                }
+                phrase.push(c);
+            }
+            anyhow::ensure!(closed, "Unterminated phrase");
+            let phrase = normalize(&phrase);
+            if phrase.is_empty() {
+                continue;
+            }
+            if negated {
+                query.excluded_phrases.push(phrase);
+            } else {
+                text.push(phrase.clone());
+                query.phrases.push(phrase);
+            }
+            continue;
+        }
+
+        let mut token = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            token.push(c);
+        }
+        if let Some((key @ ("site" | "lang" | "before" | "after"), value)) = token.split_once(':') {
+            anyhow::ensure!(!value.is_empty(), "Missing value of `{key}:`");
+            let unbounded = || DateRange {
+                from: None,
+                to: None,
+            };
+            match (key, negated) {
+                ("site", false) => filter.domains.push(value.to_lowercase()),
+                ("site", true) => filter.exclude_domains.push(value.to_lowercase()),
+                (_, true) => anyhow::bail!("`{key}:` cannot be negated"),
+                ("lang", false) => filter.lang.push(value.to_lowercase()),
+                ("before", false) => {
+                    filter.first.get_or_insert_with(unbounded).to = Some(date(key, value)?);
+                }
+                // `after:`
+                _ => filter.first.get_or_insert_with(unbounded).from = Some(date(key, value)?),
+            }
+        } else if negated {
+            if !token.is_empty() {
+                query.excluded_terms.push(token.to_lowercase());
+            }
+        } else {
+            text.push(token);
+        }
+    }
+
+    query.text = text.join(" ");
+    if query.text.is_empty() {
+        anyhow::bail!("Query has no search terms");
+    }
+    Ok(query)
+}
+
+impl Query {
+    /// Distinct lowercased words of the free text.
+    pub fn words(&self) -> Vec<String> {
+        let mut words: Vec<_> = normalize(&self.text)
+            .split(' ')
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+        words
+    }
+
+    /// Conditions on the indexed text of the results that Qdrant can check: every word
+    /// of the phrases must be present and excluded terms must be absent.
+    pub fn conditions(&self) -> (Vec<Condition>, Vec<Condition>) {
+        let must = self
+            .phrases
+            .iter()
+            .map(|phrase| Condition::matches_text("passages", phrase.clone()))
+            .collect();
+        let must_not = self
+            .excluded_terms
+            .iter()
+            .map(|term| Condition::matches_text("passages", term.clone()))
+            .collect();
+        (must, must_not)
+    }
+
+    /// Whether the passages of the payload contain every phrase in order and none of the
+    /// excluded phrases, which Qdrant filters cannot express.
+    pub fn accepts(&self, payload: &HashMap<String, Value>) -> bool {
+        if self.phrases.is_empty() && self.excluded_phrases.is_empty() {
+            return true;
+        }
+        let text = match payload
+            .get("passages")
+            .and_then(|value| value.kind.as_ref())
+        {
+            Some(Kind::ListValue(list)) => list
+                .values
+                .iter()
+                .filter_map(|value| match &value.kind {
+                    Some(Kind::StringValue(passage)) => Some(normalize(passage)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => String::new(),
+        };
+        // Padding the text and phrases with spaces only matches phrases on word boundaries.
+        let text = format!(" {text} ");
+        let contains = |phrase: &String| text.contains(&format!(" {phrase} "));
+        self.phrases.iter().all(contains) && !self.excluded_phrases.iter().any(contains)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use qdrant_client::qdrant::ListValue;
+
+    use super::*;
+
+    fn parse_query(input: &str) -> (anyhow::Result<Query>, SearchFilter) {
+        let mut filter = SearchFilter::default();
+        let query = parse(input, &mut filter);
+        (query, filter)
+    }
+
+    fn error(input: &str) -> String {
+        format!("{:#}", parse_query(input).0.unwrap_err())
+    }
+
+    fn passages(passages: &[&str]) -> HashMap<String, Value> {
+        let values = passages
+            .iter()
+            .map(|passage| Value {
+                kind: Some(Kind::StringValue(passage.to_string())),
+            })
+            .collect();
+        let list = Value {
+            kind: Some(Kind::ListValue(ListValue { values })),
+        };
+        HashMap::from([("passages".to_string(), list)])
+    }
+
+    #[test]
+    fn plain_text() {
+        let (query, filter) = parse_query("  async   runtime ");
+        let query = query.unwrap();
+        assert_eq!(query.text, "async runtime");
+        assert!(query.phrases.is_empty());
+        assert!(filter.domains.is_empty());
+    }
+
+    #[test]
+    fn quoted_phrase() {
+        let query = parse_query(r#"rust "Async, Runtime!""#).0.unwrap();
+        assert_eq!(query.text, "rust async runtime");
+        assert_eq!(query.phrases, ["async runtime"]);
+    }
+
+    #[test]
+    fn empty_phrase_is_ignored() {
+        let query = parse_query(r#"rust "" "!!""#).0.unwrap();
+        assert_eq!(query.text, "rust");
+        assert!(query.phrases.is_empty());
+    }
+
+    #[test]
+    fn unterminated_phrase() {
+        assert_eq!(error(r#"rust "async runtime"#), "Unterminated phrase");
+        assert_eq!(error(r#"rust -"async"#), "Unterminated phrase");
+    }
+
+    #[test]
+    fn operators_only() {
+        assert_eq!(error("site:docs.rs lang:en"), "Query has no search terms");
+        assert_eq!(
+            error(r#"-tokio -"async runtime""#),
+            "Query has no search terms"
+        );
+        assert_eq!(error("   "), "Query has no search terms");
+    }
+
+    #[test]
+    fn site() {
+        let (query, filter) = parse_query("site:Docs.rs -site:crates.io tokio");
+        assert_eq!(query.unwrap().text, "tokio");
+        assert_eq!(filter.domains, ["docs.rs"]);
+        assert_eq!(filter.exclude_domains, ["crates.io"]);
+    }
+
+    #[test]
+    fn excluded_term_is_not_a_domain() {
+        let (query, filter) = parse_query("-Tokio -\"Async Std\" rust");
+        let query = query.unwrap();
+        assert_eq!(query.text, "rust");
+        assert_eq!(query.excluded_terms, ["tokio"]);
+        assert_eq!(query.excluded_phrases, ["async std"]);
+        assert!(filter.exclude_domains.is_empty());
+    }
+
+    #[test]
+    fn lone_dash_is_ignored() {
+        let query = parse_query("rust - tokio").0.unwrap();
+        assert_eq!(query.text, "rust tokio");
+        assert!(query.excluded_terms.is_empty());
+    }
+
+    #[test]
+    fn unknown_operator_is_text() {
+        let query = parse_query("std::sync::Arc").0.unwrap();
+        assert_eq!(query.text, "std::sync::Arc");
+    }
+
+    #[test]
+    fn dates() {
+        let (query, filter) = parse_query("after:2024-01-01 before:2024-06-30 rust");
+        assert_eq!(query.unwrap().text, "rust");
+        let first = filter.first.unwrap();
+        assert_eq!(
+            first.from.unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(first.to.unwrap().to_rfc3339(), "2024-06-30T00:00:00+00:00");
+    }
+
+    #[test]
+    fn bad_dates() {
+        assert_eq!(
+            error("before:2024-13-01 rust"),
+            "Invalid date `2024-13-01` of `before:`, expected YYYY-MM-DD"
+        );
+        assert_eq!(
+            error("after:yesterday rust"),
+            "Invalid date `yesterday` of `after:`, expected YYYY-MM-DD"
+        );
+    }
+
+    #[test]
+    fn invalid_operators() {
+        assert_eq!(error("site: rust"), "Missing value of `site:`");
+        assert_eq!(error("-lang:en rust"), "`lang:` cannot be negated");
+        assert_eq!(
+            error("-before:2024-01-01 rust"),
+            "`before:` cannot be negated"
+        );
+    }
+
+    #[test]
+    fn words() {
+        let query = parse_query("Rust rust, async").0.unwrap();
+        assert_eq!(query.words(), ["async", "rust"]);
+    }
+
+    #[test]
+    fn accepts_phrases_on_word_boundaries() {
+        let query = parse_query(r#""async runtime" -"green threads""#)
+            .0
+            .unwrap();
+        assert!(query.accepts(&passages(&["An Async runtime, for Rust"])));
+        assert!(!query.accepts(&passages(&["An async runtimes crate"])));
+        assert!(!query.accepts(&passages(&["Async runtime", "with green threads"])));
+        assert!(!query.accepts(&HashMap::new()));
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Context;
+use qdrant_client::qdrant::{value::Kind, ScoredPoint};
+use tonic::transport::Channel;
+use utils::embed::tei::proto::{rerank_client::RerankClient, RerankRequest, RerankResponse};
+
+/// Number of candidates reranked when the request does not set it.
+pub const DEFAULT_DEPTH: u64 = 50;
+/// Maximum number of candidates reranked for a single request.
+pub const MAX_DEPTH: u64 = 200;
+
+/// Text of the point scored against the query, its title followed by its passage.
+fn text(point: &ScoredPoint) -> String {
+    ["title", "passage"]
+        .iter()
+        .filter_map(|key| match point.payload.get(*key)?.kind.as_ref()? {
+            Kind::StringValue(value) => Some(value.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Orders `points` by their cross-encoder score against `query`, which replaces their
+/// original score.
+pub async fn rerank(
+    mut client: RerankClient<Channel>,
+    query: String,
+    points: Vec<ScoredPoint>,
+) -> anyhow::Result<Vec<ScoredPoint>> {
+    if points.is_empty() {
+        return Ok(points);
+    }
+    let RerankResponse { ranks, .. } = client
+        .rerank(RerankRequest {
+            query,
+            texts: points.iter().map(text).collect(),
+            truncate: true,
+            raw_scores: false,
+            return_text: false,
+            truncation_direction: 0,
+        })
+        .await
+        .context("TEI rerank")?
+        .into_inner();
+    anyhow::ensure!(
+        ranks.len() == points.len(),
+        "TEI returned {} ranks for {} texts",
+        ranks.len(),
+        points.len()
+    );
+
+    let mut points: Vec<_> = points.into_iter().map(Some).collect();
+    let mut reranked = ranks
+        .into_iter()
+        .map(|rank| {
+            let mut point = points
+                .get_mut(rank.index as usize)
+                .and_then(Option::take)
+                .with_context(|| format!("TEI returned unexpected index {}", rank.index))?;
+            point.score = rank.score;
+            Ok(point)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    reranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(reranked)
+}
@@ -0,0 +1,73 @@
+use std::collections::{HashMap, HashSet};
+
+use qdrant_client::qdrant::{value::Kind, Value};
+
+use crate::models::{Highlight, Snippet};
+
+/// Lowercased alphanumeric words of `text` with their character offsets.
+fn words(text: &str) -> Vec<(String, Highlight)> {
+    let mut words = Vec::new();
+    let mut current: Option<(String, usize)> = None;
+    for (offset, c) in text.chars().enumerate() {
+        if c.is_alphanumeric() {
+            match &mut current {
+                Some((word, _)) => word.extend(c.to_lowercase()),
+                None => current = Some((c.to_lowercase().collect(), offset)),
+            }
+        } else if let Some((word, start)) = current.take() {
+            words.push((word, Highlight { start, end: offset }));
+        }
+    }
+    if let Some((word, start)) = current {
+        let end = text.chars().count();
+        words.push((word, Highlight { start, end }));
+    }
+    words
+}
+
+/// Query terms a snippet is matched against.
+pub struct Terms(HashSet<String>);
+
+impl Terms {
+    pub fn new(query: &str) -> Self {
+        Self(words(query).into_iter().map(|(word, _)| word).collect())
+    }
+}
+
+/// Picks the passage of the payload sharing the most terms with the query.
+///
+/// Passages are ranked by the number of distinct terms they contain, then by the
+/// number of occurrences, and the earliest one wins ties. Without any matching
+/// passage, the first one is returned without highlights.
+pub fn best(terms: &Terms, payload: &HashMap<String, Value>) -> Option<Snippet> {
+    let Some(Kind::ListValue(list)) = payload.get("passages")?.kind.as_ref() else {
+        return None;
+    };
+    let passages = list.values.iter().filter_map(|value| match &value.kind {
+        Some(Kind::StringValue(passage)) => Some(passage),
+        _ => None,
+    });
+
+    let mut best: Option<((usize, usize), Snippet)> = None;
+    for passage in passages {
+        let highlights: Vec<_> = words(passage)
+            .into_iter()
+            .filter(|(word, _)| terms.0.contains(word))
+            .collect();
+        let distinct = highlights
+            .iter()
+            .map(|(word, _)| word)
+            .collect::<HashSet<_>>()
+            .len();
+        let score = (distinct, highlights.len());
+        if best.as_ref().is_some_and(|(best, _)| *best >= score) {
+            continue;
+        }
+        let snippet = Snippet {
+            text: passage.clone(),
+            highlights: highlights.into_iter().map(|(_, range)| range).collect(),
+        };
+        best = Some((score, snippet));
+    }
+    best.map(|(_, snippet)| snippet)
+}
@@ -1,42 +1,305 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use crate::proto::embed_client::EmbedClient;
 use config::{Config, Environment};
-// use mongodb::Client;
+use mongodb::Client;
 use qdrant_client::Qdrant;
+use rabbitmq_management_client::{config::RabbitMqConfiguration, RabbitMqClientBuilder};
 use serde::Deserialize;
+use tonic::transport::Channel;
+use utils::{
+    database::{self, COLLNAME},
+    embed::{
+        self, tei::proto::rerank_client::RerankClient, Embedder, EmbedderConfig, EmbedderKind,
+        SparseEmbedder, SparseEmbedderConfig, SparseEmbedderKind,
+    },
+};
+
+use crate::{
+    access::{ApiKeys, Quota, RateLimiter},
+    admin::{proto::crawler_client::CrawlerClient, Admin},
+    analytics::Analytics,
+    cache::Cache,
+    ltr::Ranker,
+    suggest::Suggester,
+};
 
 #[derive(Clone)]
 pub struct AppState {
-    // pub mongo_client: Client,
+    pub mongo_client: Client,
     pub qdrant_client: Arc<Qdrant>,
-    pub tei_client: EmbedClient<tonic::transport::Channel>,
+    pub embedder: Arc<dyn Embedder>,
+    pub sparse_embedder: Arc<dyn SparseEmbedder>,
+    /// Cross-encoder served by TEI, reranking is unavailable without it.
+    pub reranker: Option<RerankClient<Channel>>,
+    pub cache: Option<Cache>,
+    pub suggester: Arc<Suggester>,
+    /// Writer of the query log and clicks, nothing is logged without it.
+    pub analytics: Option<Analytics>,
+    /// Learned ranking model, the fused order is kept without it.
+    pub ranker: Arc<Ranker>,
+    /// API keys of the clients, requests are anonymous without them.
+    pub api_keys: Option<Arc<ApiKeys>>,
+    /// Limiter of the request rate of clients, unlimited without it.
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Whether client IPs are read from `x-forwarded-for`.
+    pub trust_forwarded_for: bool,
+    /// Clients of the crawl services, the admin routes are disabled without them.
+    pub admin: Option<Admin>,
 }
 
 impl AppState {
     pub async fn new(config: AppConfig) -> Self {
-        // let mongo_client = Client::with_uri_str(&config.mongo_uri_read)
-        //     .await
-        //     .expect("Failed to connect to MongoDB");
+        let mongo_client = Client::with_uri_str(&config.mongo_uri_read)
+            .await
+            .expect("Failed to connect to MongoDB");
         let qdrant_client = Arc::new(
             Qdrant::from_url(&config.qdrant_uri_read)
                 .build()
                 .expect("Failed to connect to Qdrant"),
         );
-        let tei_client = EmbedClient::connect(config.tei_uri).await.unwrap();
+        // A missing collection is created with the sparse vector by the crawler.
+        if qdrant_client
+            .collection_exists(COLLNAME)
+            .await
+            .expect("Failed to reach Qdrant")
+        {
+            database::ensure_sparse_vector(&qdrant_client).await;
+        }
+        let embedder = embed::connect(EmbedderConfig {
+            kind: config.embedder,
+            tei_uri: config.tei_uri,
+            batch_window: config.embedder_batch_window_ms.map(Duration::from_millis),
+            model_dir: config.embedder_model_dir,
+            dimension: config.vector_dim,
+        })
+        .await
+        .expect("Failed to initialize embedder");
+        let sparse_embedder = embed::connect_sparse(SparseEmbedderConfig {
+            kind: config.sparse_embedder,
+            tei_uri: config.tei_sparse_uri,
+        })
+        .await
+        .expect("Failed to initialize sparse embedder");
+        let reranker = match config.tei_rerank_uri {
+            Some(uri) => Some(
+                RerankClient::connect(uri)
+                    .await
+                    .expect("Failed to connect to the TEI reranker"),
+            ),
+            None => None,
+        };
+        let redis_client = config
+            .redis_uri
+            .map(|uri| redis::Client::open(uri).expect("Invalid Redis URI"));
+        let cache = redis_client.clone().map(|client| Cache {
+            client,
+            embedding_ttl: config.cache_embedding_ttl_secs,
+            results_ttl: config.cache_results_ttl_secs,
+            version_step: config.cache_version_step,
+        });
+        let rate_limiter = config.rate_limit.then(|| {
+            let client = redis_client
+                .clone()
+                .expect("Rate limiting requires REDIS_URI");
+            let ip_quota = Quota {
+                rate: config.rate_limit_ip_rate,
+                burst: config.rate_limit_ip_burst,
+            };
+            Arc::new(RateLimiter::new(client, ip_quota))
+        });
+        let api_keys = config.api_keys_file.map(|path| {
+            let default = Quota {
+                rate: config.rate_limit_key_rate,
+                burst: config.rate_limit_key_burst,
+            };
+            let default_ip = Quota {
+                rate: config.rate_limit_key_ip_rate,
+                burst: config.rate_limit_key_ip_burst,
+            };
+            Arc::new(
+                ApiKeys::load(&path, default, default_ip, config.api_key_required)
+                    .expect("Failed to load API keys"),
+            )
+        });
+        let admin = config.crawler_uri.map(|uri| {
+            // Connected on first use, searches do not depend on the crawler being up.
+            let channel = Channel::from_shared(uri)
+                .expect("Invalid crawler URI")
+                .connect_lazy();
+            let management_client = config.rabbitmq_api_url.map(|url| {
+                RabbitMqClientBuilder::new(RabbitMqConfiguration {
+                    rabbitmq_api_url: url,
+                    rabbitmq_username: config.amqp_usr.unwrap_or_default(),
+                    rabbitmq_password: config.amqp_pwd.unwrap_or_default(),
+                })
+                .build()
+                .expect("Failed to build RabbitMQ management client")
+            });
+            Admin {
+                crawler_client: CrawlerClient::new(channel),
+                management_client,
+                redis_client: redis_client.clone(),
+            }
+        });
+        let suggester = Arc::new(Suggester::new(mongo_client.clone(), redis_client));
+        let analytics = match config.mongo_uri_write {
+            Some(uri) => Some(
+                Analytics::connect(&uri)
+                    .await
+                    .expect("Failed to initialize analytics"),
+            ),
+            None => None,
+        };
+        let ranker = Arc::new(Ranker::new(
+            config.ltr_model,
+            config.ltr_depth,
+            config.ltr_traffic,
+        ));
         Self {
-            // mongo_client,
+            mongo_client,
             qdrant_client,
-            tei_client,
+            embedder,
+            sparse_embedder,
+            reranker,
+            cache,
+            suggester,
+            analytics,
+            ranker,
+            api_keys,
+            rate_limiter,
+            trust_forwarded_for: config.trust_forwarded_for,
+            admin,
         }
     }
 }
 
 #[derive(Deserialize)]
 pub struct AppConfig {
-    // pub mongo_uri_read: String,
+    pub mongo_uri_read: String,
+    /// MongoDB the query log and clicks are written to.
+    pub mongo_uri_write: Option<String>,
     pub qdrant_uri_read: String,
-    pub tei_uri: String,
+    #[serde(default)]
+    pub embedder: EmbedderKind,
+    pub tei_uri: Option<String>,
+    /// Batches concurrent TEI gRPC requests arriving within this many milliseconds.
+    pub embedder_batch_window_ms: Option<u64>,
+    pub embedder_model_dir: Option<PathBuf>,
+    pub vector_dim: u64,
+    #[serde(default)]
+    pub sparse_embedder: SparseEmbedderKind,
+    /// Address of TEI serving a SPLADE model, required by the `tei` sparse embedder.
+    pub tei_sparse_uri: Option<String>,
+    /// Address of TEI serving a cross-encoder model, enables reranking.
+    pub tei_rerank_uri: Option<String>,
+    /// Redis caching query embeddings and results and counting queries for suggestions,
+    /// nothing is cached or counted without it.
+    pub redis_uri: Option<String>,
+    #[serde(default = "default_embedding_ttl")]
+    pub cache_embedding_ttl_secs: u64,
+    #[serde(default = "default_results_ttl")]
+    pub cache_results_ttl_secs: u64,
+    /// Number of index changes after which cached results are discarded.
+    #[serde(default = "default_version_step")]
+    pub cache_version_step: u64,
+    /// Interval between rebuilds of the suggestion index.
+    #[serde(default = "default_suggest_rebuild")]
+    pub suggest_rebuild_secs: u64,
+    /// File of the learned ranking model written by `train-ranker`, reloaded when it
+    /// changes.
+    pub ltr_model: Option<PathBuf>,
+    /// Number of top candidates rescored by the learned ranking model.
+    #[serde(default = "default_ltr_depth")]
+    pub ltr_depth: usize,
+    /// Share of sessions ranked by the learned model, the others keep the fused order.
+    #[serde(default = "default_ltr_traffic")]
+    pub ltr_traffic: f32,
+    /// Interval between checks of the model file.
+    #[serde(default = "default_ltr_reload")]
+    pub ltr_reload_secs: u64,
+    /// TOML, YAML or JSON file of the API keys of the clients, sent in `x-api-key`.
+    pub api_keys_file: Option<PathBuf>,
+    /// Rejects requests without an API key rather than limiting them by IP.
+    #[serde(default)]
+    pub api_key_required: bool,
+    /// Limits the request rate of every API key and IP in Redis, shared by replicas.
+    #[serde(default)]
+    pub rate_limit: bool,
+    /// Requests per second of an API key without its own quota.
+    #[serde(default = "default_key_rate")]
+    pub rate_limit_key_rate: f64,
+    #[serde(default = "default_key_burst")]
+    pub rate_limit_key_burst: u64,
+    /// Requests per second of each IP using an API key without its own IP quota.
+    #[serde(default = "default_key_rate")]
+    pub rate_limit_key_ip_rate: f64,
+    #[serde(default = "default_key_burst")]
+    pub rate_limit_key_ip_burst: u64,
+    /// Requests per second of an IP without an API key.
+    #[serde(default = "default_ip_rate")]
+    pub rate_limit_ip_rate: f64,
+    #[serde(default = "default_ip_burst")]
+    pub rate_limit_ip_burst: u64,
+    /// Reads client IPs from `x-forwarded-for`, only behind a proxy setting it.
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    /// Crawler service the admin routes proxy to, enables them.
+    pub crawler_uri: Option<String>,
+    /// RabbitMQ management API, which lists the domain queues for the admin routes.
+    pub rabbitmq_api_url: Option<String>,
+    pub amqp_usr: Option<String>,
+    pub amqp_pwd: Option<String>,
+    /// Directory of the built frontend, served at the root when it holds an `index.html`.
+    #[serde(default = "default_frontend_dir")]
+    pub frontend_dir: PathBuf,
+}
+
+fn default_embedding_ttl() -> u64 {
+    60 * 60 * 24
+}
+
+fn default_results_ttl() -> u64 {
+    60 * 5
+}
+
+fn default_version_step() -> u64 {
+    100
+}
+
+fn default_suggest_rebuild() -> u64 {
+    60 * 10
+}
+
+fn default_ltr_depth() -> usize {
+    100
+}
+
+fn default_ltr_traffic() -> f32 {
+    0.5
+}
+
+fn default_ltr_reload() -> u64 {
+    30
+}
+
+fn default_key_rate() -> f64 {
+    10.0
+}
+
+fn default_key_burst() -> u64 {
+    20
+}
+
+fn default_ip_rate() -> f64 {
+    2.0
+}
+
+fn default_ip_burst() -> u64 {
+    10
+}
+
+fn default_frontend_dir() -> PathBuf {
+    PathBuf::from("frontend/dist")
 }
 
 impl AppConfig {
@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::Context;
+use fst::{automaton::Str, Automaton, IntoStreamer, Map, Streamer};
+use mongodb::bson::doc;
+use mongodm::{f, ToRepository};
+use redis::AsyncCommands;
+use utils::{
+    database::{TextsProjection, DATABASE},
+    redis::Key,
+};
+
+/// Number of suggestions returned when the request does not set it.
+pub const DEFAULT_LIMIT: usize = 8;
+/// Maximum number of suggestions of a request.
+pub const MAX_LIMIT: usize = 20;
+/// Maximum number of characters of a suggestion.
+const MAX_LENGTH: usize = 80;
+/// Number of most frequent queries kept in Redis and indexed.
+const MAX_QUERIES: isize = 10_000;
+/// Weight of a successful search relative to a page title or anchor text.
+const QUERY_WEIGHT: u64 = 10;
+/// Maximum number of completions of a prefix compared, which bounds the latency of
+/// short prefixes.
+const MAX_CANDIDATES: usize = 10_000;
+
+/// Lowercased `text` with its whitespace collapsed.
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Prefix index of completions weighted by popularity: past successful queries, page
+/// titles and anchor texts.
+///
+/// The index is an FST held in memory and rebuilt periodically from MongoDB and the
+/// queries counted in Redis, lookups never leave the process.
+pub struct Suggester {
+    index: RwLock<Arc<Map<Vec<u8>>>>,
+    mongo_client: mongodb::Client,
+    redis_client: Option<redis::Client>,
+}
+
+impl Suggester {
+    pub fn new(mongo_client: mongodb::Client, redis_client: Option<redis::Client>) -> Self {
+        Self {
+            index: RwLock::new(Arc::new(Map::default())),
+            mongo_client,
+            redis_client,
+        }
+    }
+
+    /// Most popular completions of `prefix`, most popular first.
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let prefix = normalize(prefix);
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+        let index = self.index.read().unwrap().clone();
+        let mut stream = index.search(Str::new(&prefix).starts_with()).into_stream();
+        let mut candidates = Vec::new();
+        while let Some((key, weight)) = stream.next() {
+            candidates.push((weight, key.to_vec()));
+            if candidates.len() == MAX_CANDIDATES {
+                break;
+            }
+        }
+        candidates.sort_by(|(a, _), (b, _)| b.cmp(a));
+        candidates
+            .into_iter()
+            .take(limit)
+            .filter_map(|(_, key)| String::from_utf8(key).ok())
+            .collect()
+    }
+
+    /// Counts a query that returned results, without a Redis client it is not counted.
+    pub async fn record(&self, query: &str) {
+        let Some(redis_client) = &self.redis_client else {
+            return;
+        };
+        let query = normalize(query);
+        if query.is_empty() || query.chars().count() > MAX_LENGTH {
+            return;
+        }
+        let result = async {
+            let mut conn = redis_client.get_multiplexed_tokio_connection().await?;
+            conn.zincr::<_, _, _, ()>(Key::Queries, query, 1).await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to count query: {e:#}");
+        }
+    }
+
+    async fn queries(&self, weights: &mut BTreeMap<String, u64>) -> anyhow::Result<()> {
+        let Some(redis_client) = &self.redis_client else {
+            return Ok(());
+        };
+        let mut conn = redis_client.get_multiplexed_tokio_connection().await?;
+        // Only the most frequent queries are kept, the set would grow forever otherwise.
+        conn.zremrangebyrank::<_, ()>(Key::Queries, 0, -MAX_QUERIES - 1)
+            .await?;
+        let queries: Vec<(String, f64)> = conn
+            .zrevrange_withscores(Key::Queries, 0, MAX_QUERIES - 1)
+            .await?;
+        for (query, count) in queries {
+            *weights.entry(query).or_default() += count as u64 * QUERY_WEIGHT;
+        }
+        Ok(())
+    }
+
+    async fn texts(&self, weights: &mut BTreeMap<String, u64>) -> anyhow::Result<()> {
+        let mut cursor = self
+            .mongo_client
+            .database(DATABASE)
+            .repository::<TextsProjection>()
+            .find(doc! {})
+            .projection(
+                doc! { f!(metadata in TextsProjection): 1, f!(anchors in TextsProjection): 1 },
+            )
+            .await?;
+        while cursor.advance().await? {
+            let TextsProjection { metadata, anchors } = cursor.deserialize_current()?;
+            for text in metadata.title.into_iter().chain(anchors) {
+                let text = normalize(&text);
+                if !text.is_empty() && text.chars().count() <= MAX_LENGTH {
+                    *weights.entry(text).or_default() += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuilds the index from the current titles, anchor texts and query counts.
+    pub async fn rebuild(&self) -> anyhow::Result<()> {
+        let mut weights = BTreeMap::new();
+        self.texts(&mut weights)
+            .await
+            .context("Failed to read page texts")?;
+        self.queries(&mut weights)
+            .await
+            .context("Failed to read query counts")?;
+        let count = weights.len();
+        let index = Map::from_iter(weights).context("Failed to build the index")?;
+        *self.index.write().unwrap() = Arc::new(index);
+        tracing::info!(count, "Rebuilt suggestion index");
+        Ok(())
+    }
+
+    /// Rebuilds the index now and then every `interval`.
+    pub fn spawn_rebuild(self: Arc<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.rebuild().await {
+                    tracing::error!("Failed to rebuild suggestion index: {e:#}");
+                }
+            }
+        });
+    }
+}
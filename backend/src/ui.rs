@@ -0,0 +1,199 @@
+use askama::Template;
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use serde::Deserialize;
+use url::form_urlencoded;
+
+use crate::{
+    analytics,
+    models::{query_param, FilterParams, SearchRequest, SearchResponse, Snippet},
+    state::AppState,
+    validate,
+};
+
+/// Query string of the search page, the form fields as typed.
+#[derive(Deserialize, Default, Clone)]
+pub struct SearchForm {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default)]
+    pub domains: String,
+    #[serde(default)]
+    pub exclude_domains: String,
+    #[serde(default)]
+    pub lang: String,
+    #[serde(default)]
+    pub mime: String,
+    pub offset: Option<u64>,
+}
+
+impl SearchForm {
+    /// Link to the page of results starting at `offset`.
+    fn href(&self, offset: u64) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.append_pair("q", &self.q);
+        for (name, value) in [
+            ("domains", &self.domains),
+            ("exclude_domains", &self.exclude_domains),
+            ("lang", &self.lang),
+            ("mime", &self.mime),
+        ] {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+        if offset > 0 {
+            query.append_pair("offset", &offset.to_string());
+        }
+        format!("/search?{}", query.finish())
+    }
+
+    fn request(&self) -> SearchRequest {
+        let some = |value: &String| (!value.trim().is_empty()).then(|| value.clone());
+        let filter = FilterParams {
+            domains: some(&self.domains),
+            exclude_domains: some(&self.exclude_domains),
+            lang: some(&self.lang),
+            mime: some(&self.mime),
+            first_from: None,
+            first_to: None,
+            last_from: None,
+            last_to: None,
+        };
+        SearchRequest {
+            query: self.q.clone(),
+            limit: Some(validate::DEFAULT_LIMIT),
+            offset: self.offset,
+            cursor: None,
+            dense_weight: None,
+            sparse_weight: None,
+            rerank: None,
+            rerank_depth: None,
+            filter: filter.into(),
+            max_per_domain: None,
+            mmr_lambda: None,
+            explain: None,
+            ltr: None,
+        }
+    }
+}
+
+/// Part of a snippet, highlighted if it matches a query term.
+pub struct Segment {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+fn segments(snippet: Snippet) -> Vec<Segment> {
+    let chars: Vec<char> = snippet.text.chars().collect();
+    let mut segments = Vec::new();
+    let mut position = 0;
+    let mut push = |start: usize, end: usize, highlighted: bool| {
+        if start < end {
+            segments.push(Segment {
+                text: chars[start..end].iter().collect(),
+                highlighted,
+            });
+        }
+    };
+    for highlight in snippet.highlights {
+        let start = highlight.start.clamp(position, chars.len());
+        let end = highlight.end.clamp(start, chars.len());
+        push(position, start, false);
+        push(start, end, true);
+        position = end;
+    }
+    push(position, chars.len(), false);
+    segments
+}
+
+pub struct ResultView {
+    pub title: String,
+    pub url: String,
+    pub snippet: Vec<Segment>,
+}
+
+/// Search page rendered without JavaScript: the form, then the results of its query.
+#[derive(Template)]
+#[template(path = "search.html")]
+pub struct SearchPage {
+    pub form: SearchForm,
+    pub results: Vec<ResultView>,
+    pub total: Option<u64>,
+    /// Messages of a failed search.
+    pub errors: Vec<String>,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+impl SearchPage {
+    fn new(form: SearchForm) -> Self {
+        Self {
+            form,
+            results: Vec::new(),
+            total: None,
+            errors: Vec::new(),
+            previous: None,
+            next: None,
+        }
+    }
+
+    fn results(mut self, response: SearchResponse) -> Self {
+        let offset = self.form.offset.unwrap_or(0);
+        let limit = validate::DEFAULT_LIMIT;
+        self.previous = (offset > 0).then(|| self.form.href(offset.saturating_sub(limit)));
+        self.next = response
+            .next_cursor
+            .is_some()
+            .then(|| self.form.href(offset + limit));
+        self.total = response.total;
+        self.results = response
+            .matches
+            .into_iter()
+            .map(|result| ResultView {
+                title: result.title,
+                url: result.url,
+                snippet: result.snippet.map(segments).unwrap_or_default(),
+            })
+            .collect();
+        self
+    }
+}
+
+#[axum::debug_handler]
+pub async fn search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    form: Result<Query<SearchForm>, QueryRejection>,
+) -> (StatusCode, SearchPage) {
+    let form = match form {
+        Ok(Query(form)) => form,
+        Err(e) => {
+            let mut page = SearchPage::new(SearchForm::default());
+            page.errors.push(e.body_text());
+            return (StatusCode::BAD_REQUEST, page);
+        }
+    };
+    let mut page = SearchPage::new(form.clone());
+    if form.q.trim().is_empty() {
+        return (StatusCode::OK, page);
+    }
+
+    let request = form.request();
+    if let Err(fields) = validate::search(&request) {
+        page.errors = fields
+            .into_iter()
+            .map(|error| format!("{}: {}", query_param(&error.field), error.message))
+            .collect();
+        return (StatusCode::BAD_REQUEST, page);
+    }
+    match crate::search(state, request, analytics::session(&headers)).await {
+        Ok((_, response)) => (StatusCode::OK, page.results(response)),
+        Err(error) => {
+            page.errors.push(error.message);
+            (error.error.status_code(), page)
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use mongodb::bson::Uuid;
+
+use crate::{
+    cursor::Cursor,
+    models::{
+        error::FieldError, ClickRequest, DateRange, DeleteParams, RecrawlRequest, SearchFilter,
+        SearchRequest, SeedRequest, SimilarRequest, SuggestParams,
+    },
+    query, rerank, suggest,
+};
+
+/// Number of results of a page when the request does not set it.
+pub const DEFAULT_LIMIT: u64 = 10;
+/// Maximum number of results of a page.
+pub const MAX_LIMIT: u64 = 50;
+/// Maximum number of results skipped, deeper pages are too costly to rank.
+pub const MAX_OFFSET: u64 = 1000;
+/// Maximum number of characters of a query.
+pub const MAX_QUERY_LENGTH: usize = 1024;
+/// Maximum number of URLs of a seed request, which are crawled before the response.
+pub const MAX_SEEDS: usize = 100;
+
+/// Invalid fields found so far.
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn check(&mut self, valid: bool, field: &str, message: impl Into<String>) {
+        if !valid {
+            self.0.push(FieldError {
+                field: field.to_string(),
+                message: message.into(),
+            });
+        }
+    }
+
+    fn page(&mut self, limit: Option<u64>, offset: Option<u64>) {
+        if let Some(limit) = limit {
+            self.check(limit >= 1, "limit", "must be at least 1");
+            self.check(
+                limit <= MAX_LIMIT,
+                "limit",
+                format!("must be at most {MAX_LIMIT}"),
+            );
+        }
+        if let Some(offset) = offset {
+            self.check(
+                offset <= MAX_OFFSET,
+                "offset",
+                format!("must be at most {MAX_OFFSET}"),
+            );
+        }
+    }
+
+    fn range(&mut self, range: &Option<DateRange>, field: &str) {
+        if let Some(DateRange {
+            from: Some(from),
+            to: Some(to),
+        }) = range
+        {
+            self.check(
+                from <= to,
+                &format!("{field}.from"),
+                "must not be after `to`",
+            );
+        }
+    }
+
+    fn filter(&mut self, filter: &SearchFilter) {
+        self.range(&filter.first, "filter.first");
+        self.range(&filter.last, "filter.last");
+    }
+
+    fn url(&mut self, url: &str, field: &str) {
+        let valid = url::Url::parse(url)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+        self.check(valid, field, "must be an HTTP(S) URL");
+    }
+
+    fn weight(&mut self, weight: Option<f32>, field: &str) {
+        if let Some(weight) = weight {
+            self.check(
+                weight.is_finite() && weight >= 0.0,
+                field,
+                "must be a non-negative number",
+            );
+        }
+    }
+
+    fn finish(self) -> Result<(), Vec<FieldError>> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self.0)
+        }
+    }
+}
+
+/// Checks every field of a search, including its query operators.
+pub fn search(request: &SearchRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    let length = request.query.chars().count();
+    if request.query.trim().is_empty() {
+        errors.check(false, "query", "must not be empty");
+    } else if length > MAX_QUERY_LENGTH {
+        errors.check(
+            false,
+            "query",
+            format!("must be at most {MAX_QUERY_LENGTH} characters"),
+        );
+    } else if let Err(e) = query::parse(&request.query, &mut request.filter.clone()) {
+        errors.check(false, "query", format!("{e:#}"));
+    }
+    errors.page(request.limit, request.offset);
+    if let Some(cursor) = &request.cursor {
+        errors.check(
+            request.offset.is_none(),
+            "cursor",
+            "cannot be combined with `offset`",
+        );
+        match Cursor::decode(cursor) {
+            Ok(cursor) => errors.check(
+                cursor.offset <= MAX_OFFSET,
+                "cursor",
+                format!("is more than {MAX_OFFSET} results deep"),
+            ),
+            Err(_) => errors.check(false, "cursor", "is not a valid cursor"),
+        }
+    }
+    errors.filter(&request.filter);
+    errors.weight(request.dense_weight, "dense_weight");
+    errors.weight(request.sparse_weight, "sparse_weight");
+    errors.check(
+        !(request.dense_weight == Some(0.0) && request.sparse_weight == Some(0.0)),
+        "dense_weight",
+        "cannot be 0 along with `sparse_weight`",
+    );
+    if let Some(depth) = request.rerank_depth {
+        errors.check(
+            (1..=rerank::MAX_DEPTH).contains(&depth),
+            "rerank_depth",
+            format!("must be between 1 and {}", rerank::MAX_DEPTH),
+        );
+    }
+    if let Some(max) = request.max_per_domain {
+        errors.check(max >= 1, "max_per_domain", "must be at least 1");
+    }
+    if let Some(lambda) = request.mmr_lambda {
+        errors.check(
+            (0.0..=1.0).contains(&lambda),
+            "mmr_lambda",
+            "must be between 0 and 1",
+        );
+    }
+    errors.finish()
+}
+
+pub fn similar(request: &SimilarRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.page(request.limit, request.offset);
+    errors.filter(&request.filter);
+    for id in &request.negative {
+        errors.check(
+            Uuid::parse_str(id).is_ok(),
+            "negative",
+            format!("`{id}` is not a valid page id"),
+        );
+    }
+    errors.finish()
+}
+
+pub fn suggest(params: &SuggestParams) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.check(!params.q.trim().is_empty(), "q", "must not be empty");
+    errors.check(
+        params.q.chars().count() <= MAX_QUERY_LENGTH,
+        "q",
+        format!("must be at most {MAX_QUERY_LENGTH} characters"),
+    );
+    if let Some(limit) = params.limit {
+        errors.check(
+            (1..=suggest::MAX_LIMIT).contains(&limit),
+            "limit",
+            format!("must be between 1 and {}", suggest::MAX_LIMIT),
+        );
+    }
+    errors.finish()
+}
+
+pub fn click(request: &ClickRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.check(
+        Uuid::parse_str(&request.search_id).is_ok(),
+        "search_id",
+        "is not a valid search id",
+    );
+    errors.check(
+        Uuid::parse_str(&request.page_id).is_ok(),
+        "page_id",
+        "is not a valid page id",
+    );
+    errors.check(
+        request.position < MAX_OFFSET + MAX_LIMIT,
+        "position",
+        format!("must be less than {}", MAX_OFFSET + MAX_LIMIT),
+    );
+    errors.finish()
+}
+
+pub fn seed(request: &SeedRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.check(!request.urls.is_empty(), "urls", "must not be empty");
+    errors.check(
+        request.urls.len() <= MAX_SEEDS,
+        "urls",
+        format!("must have at most {MAX_SEEDS} URLs"),
+    );
+    for (i, url) in request.urls.iter().enumerate() {
+        errors.url(url, &format!("urls.{i}"));
+    }
+    errors.finish()
+}
+
+pub fn recrawl(request: &RecrawlRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.url(&request.url, "url");
+    errors.finish()
+}
+
+pub fn delete(params: &DeleteParams) -> Result<(), Vec<FieldError>> {
+    let mut errors = Errors::default();
+    match (&params.url, &params.prefix) {
+        (Some(url), None) => errors.url(url, "url"),
+        (None, Some(prefix)) => errors.url(prefix, "prefix"),
+        _ => errors.check(
+            false,
+            "url",
+            "exactly one of `url` and `prefix` must be set",
+        ),
+    }
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+
+    fn request(query: &str) -> SearchRequest {
+        SearchRequest {
+            query: query.to_string(),
+            limit: None,
+            offset: None,
+            cursor: None,
+            dense_weight: None,
+            sparse_weight: None,
+            rerank: None,
+            rerank_depth: None,
+            filter: SearchFilter::default(),
+            max_per_domain: None,
+            mmr_lambda: None,
+            explain: None,
+            ltr: None,
+        }
+    }
+
+    /// Names of the invalid fields, empty if the check passed.
+    fn fields(result: Result<(), Vec<FieldError>>) -> Vec<String> {
+        result
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|error| error.field)
+            .collect()
+    }
+
+    fn search_fields(request: &SearchRequest) -> Vec<String> {
+        fields(search(request))
+    }
+
+    fn delete_fields(url: Option<&str>, prefix: Option<&str>) -> Vec<String> {
+        fields(delete(&DeleteParams {
+            url: url.map(str::to_string),
+            prefix: prefix.map(str::to_string),
+        }))
+    }
+
+    fn cursor(offset: u64) -> String {
+        Cursor {
+            offset,
+            id: "a".to_string(),
+            score: 0.5,
+        }
+        .encode()
+    }
+
+    #[test]
+    fn valid_search() {
+        assert!(search_fields(&request("rust")).is_empty());
+    }
+
+    #[test]
+    fn query_length() {
+        assert_eq!(search_fields(&request("")), ["query"]);
+        assert_eq!(search_fields(&request(" \t ")), ["query"]);
+        let longest = "é".repeat(MAX_QUERY_LENGTH);
+        assert!(search_fields(&request(&longest)).is_empty());
+        assert_eq!(search_fields(&request(&format!("{longest}e"))), ["query"]);
+        assert_eq!(search_fields(&request("site:docs.rs")), ["query"]);
+    }
+
+    #[test]
+    fn limit_and_offset() {
+        let page = |limit, offset| SearchRequest {
+            limit,
+            offset,
+            ..request("rust")
+        };
+        assert!(search_fields(&page(Some(1), Some(0))).is_empty());
+        assert!(search_fields(&page(Some(MAX_LIMIT), Some(MAX_OFFSET))).is_empty());
+        assert_eq!(search_fields(&page(Some(0), None)), ["limit"]);
+        assert_eq!(search_fields(&page(Some(MAX_LIMIT + 1), None)), ["limit"]);
+        assert_eq!(search_fields(&page(None, Some(MAX_OFFSET + 1))), ["offset"]);
+    }
+
+    #[test]
+    fn cursor_depth() {
+        let with = |cursor, offset| SearchRequest {
+            cursor: Some(cursor),
+            offset,
+            ..request("rust")
+        };
+        assert!(search_fields(&with(cursor(MAX_OFFSET), None)).is_empty());
+        assert_eq!(
+            search_fields(&with(cursor(MAX_OFFSET + 1), None)),
+            ["cursor"]
+        );
+        assert_eq!(
+            search_fields(&with("not a cursor".to_string(), None)),
+            ["cursor"]
+        );
+        assert_eq!(search_fields(&with(cursor(10), Some(10))), ["cursor"]);
+    }
+
+    #[test]
+    fn weights() {
+        let weighted = |dense, sparse| SearchRequest {
+            dense_weight: dense,
+            sparse_weight: sparse,
+            ..request("rust")
+        };
+        assert!(search_fields(&weighted(Some(0.0), Some(1.0))).is_empty());
+        assert!(search_fields(&weighted(Some(1.0), Some(0.0))).is_empty());
+        assert_eq!(
+            search_fields(&weighted(Some(0.0), Some(0.0))),
+            ["dense_weight"]
+        );
+        assert_eq!(search_fields(&weighted(Some(-0.1), None)), ["dense_weight"]);
+        assert_eq!(
+            search_fields(&weighted(None, Some(f32::NAN))),
+            ["sparse_weight"]
+        );
+        assert_eq!(
+            search_fields(&weighted(Some(f32::INFINITY), None)),
+            ["dense_weight"]
+        );
+    }
+
+    #[test]
+    fn ranking_options() {
+        let depth = |depth| SearchRequest {
+            rerank_depth: Some(depth),
+            ..request("rust")
+        };
+        assert!(search_fields(&depth(1)).is_empty());
+        assert!(search_fields(&depth(rerank::MAX_DEPTH)).is_empty());
+        assert_eq!(search_fields(&depth(0)), ["rerank_depth"]);
+        assert_eq!(
+            search_fields(&depth(rerank::MAX_DEPTH + 1)),
+            ["rerank_depth"]
+        );
+
+        let max = |max| SearchRequest {
+            max_per_domain: Some(max),
+            ..request("rust")
+        };
+        assert!(search_fields(&max(1)).is_empty());
+        assert_eq!(search_fields(&max(0)), ["max_per_domain"]);
+
+        let lambda = |lambda| SearchRequest {
+            mmr_lambda: Some(lambda),
+            ..request("rust")
+        };
+        assert!(search_fields(&lambda(0.0)).is_empty());
+        assert!(search_fields(&lambda(1.0)).is_empty());
+        assert_eq!(search_fields(&lambda(-0.01)), ["mmr_lambda"]);
+        assert_eq!(search_fields(&lambda(1.01)), ["mmr_lambda"]);
+        assert_eq!(search_fields(&lambda(f32::NAN)), ["mmr_lambda"]);
+    }
+
+    #[test]
+    fn date_ranges() {
+        let now = Utc::now();
+        let ranged = |from, to| {
+            let mut request = request("rust");
+            request.filter.last = Some(DateRange { from, to });
+            request
+        };
+        assert!(search_fields(&ranged(Some(now), Some(now))).is_empty());
+        assert!(search_fields(&ranged(Some(now), None)).is_empty());
+        assert_eq!(
+            search_fields(&ranged(Some(now), Some(now - Duration::seconds(1)))),
+            ["filter.last.from"]
+        );
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let request = SearchRequest {
+            limit: Some(0),
+            offset: Some(MAX_OFFSET + 1),
+            mmr_lambda: Some(2.0),
+            ..request("")
+        };
+        assert_eq!(
+            search_fields(&request),
+            ["query", "limit", "offset", "mmr_lambda"]
+        );
+    }
+
+    #[test]
+    fn delete_target() {
+        assert!(delete_fields(Some("https://example.com/page"), None).is_empty());
+        assert!(delete_fields(None, Some("http://example.com")).is_empty());
+        assert_eq!(delete_fields(None, None), ["url"]);
+        assert_eq!(
+            delete_fields(Some("https://example.com/"), Some("https://example.com/")),
+            ["url"]
+        );
+    }
+
+    #[test]
+    fn delete_urls() {
+        assert_eq!(delete_fields(Some("example.com/page"), None), ["url"]);
+        assert_eq!(delete_fields(Some("ftp://example.com/"), None), ["url"]);
+        assert_eq!(delete_fields(None, Some("file:///etc")), ["prefix"]);
+        assert_eq!(delete_fields(None, Some("https://")), ["prefix"]);
+        assert_eq!(delete_fields(None, Some("")), ["prefix"]);
+    }
+}
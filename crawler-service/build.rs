@@ -1,5 +1,4 @@
 fn main() {
-    tonic_build::compile_protos("../proto/tei.proto").unwrap();
     tonic_build::compile_protos("../proto/crawler.proto").unwrap();
     tonic_build::compile_protos("../proto/messaging.proto").unwrap();
 }
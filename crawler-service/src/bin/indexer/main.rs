@@ -0,0 +1,272 @@
+mod source;
+
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::Parser;
+use crawler_service::{
+    core::{extract, store, Extracted, PageContent},
+    state::IndexState,
+};
+use futures::StreamExt;
+use mongodb::{bson::doc, options::CountOptions};
+use mongodm::{f, ToRepository};
+use source::{Document, Format};
+use tokio::time::Instant;
+use url::Url;
+use utils::{
+    database::{Page, DATABASE},
+    embed::{EmbedderConfig, EmbedderKind, SparseEmbedderConfig, SparseEmbedderKind},
+};
+
+/// Builds the index from WARC files or local directories of HTML, Markdown and PDF files.
+///
+/// Documents go through the same extraction, `Page` upsert and embedding path as the
+/// crawler. Documents whose content hash is already stored are skipped, so an
+/// interrupted run can be resumed by running it again.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// WARC files (`.warc` or `.warc.gz`), directories or single documents
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// URL that paths of local documents are resolved against, `file://` URLs otherwise
+    #[arg(long)]
+    base_url: Option<Url>,
+    /// Number of documents processed per batch
+    #[arg(long, default_value_t = 64)]
+    batch_size: usize,
+    /// Number of documents of a batch processed concurrently
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+    /// Re-index documents even if their content hash is unchanged
+    #[arg(long)]
+    force: bool,
+    #[arg(long, env)]
+    mongo_uri_write: String,
+    #[arg(long, env)]
+    qdrant_uri_write: String,
+    /// Redis counting index changes, which expires the search results cached by the backend
+    #[arg(long, env)]
+    redis_uri: Option<String>,
+    /// Embedding backend: `tei_grpc`, `tei_http`, `local` or `hash`
+    #[arg(long, env, default_value = "tei_grpc")]
+    embedder: EmbedderKind,
+    #[arg(long, env)]
+    tei_uri: Option<String>,
+    /// Batches concurrent TEI gRPC requests arriving within this many milliseconds
+    #[arg(long, env)]
+    embedder_batch_window_ms: Option<u64>,
+    #[arg(long, env)]
+    embedder_model_dir: Option<PathBuf>,
+    #[arg(long, env)]
+    vector_dim: u64,
+    /// Sparse embedding backend: `bm25` or `tei`
+    #[arg(long, env, default_value = "bm25")]
+    sparse_embedder: SparseEmbedderKind,
+    /// Address of TEI serving a SPLADE model, for the `tei` sparse embedder
+    #[arg(long, env)]
+    tei_sparse_uri: Option<String>,
+}
+
+enum Outcome {
+    Indexed,
+    Skipped,
+}
+
+#[derive(Default)]
+struct Progress {
+    indexed: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+impl Progress {
+    fn total(&self) -> usize {
+        self.indexed + self.skipped + self.failed
+    }
+}
+
+async fn is_indexed(url: &Url, hash: &str, state: &IndexState) -> anyhow::Result<bool> {
+    let filter = doc! {
+        f!(url in Page): url.as_str(),
+        f!(sha256 in Page): hash,
+    };
+    let count_options = CountOptions::builder().limit(1).build();
+    let count = state
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Page>()
+        .count_documents(filter)
+        .with_options(count_options)
+        .await
+        .context("Failed to check if document was already indexed")?;
+    Ok(count > 0)
+}
+
+fn extract_document(url: &Url, format: Format, content: Vec<u8>) -> anyhow::Result<PageContent> {
+    match format {
+        Format::Html => {
+            let Extracted {
+                title,
+                body,
+                lang,
+                links,
+                anchors,
+            } = extract(url, &String::from_utf8_lossy(&content));
+            Ok(PageContent {
+                title,
+                body,
+                lang,
+                mime: "text/html",
+                links: links.len(),
+                anchors,
+            })
+        }
+        Format::Markdown => {
+            let markdown = String::from_utf8_lossy(&content);
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(&mut html, pulldown_cmark::Parser::new(&markdown));
+            let Extracted {
+                title,
+                body,
+                links,
+                anchors,
+                ..
+            } = extract(url, &html);
+            Ok(PageContent {
+                title,
+                body,
+                lang: None,
+                mime: "text/markdown",
+                links: links.len(),
+                anchors,
+            })
+        }
+        Format::Pdf => {
+            let text = pdf_extract::extract_text_from_mem(&content).context("PDF extraction")?;
+            let body = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            Ok(PageContent {
+                title: None,
+                body,
+                lang: None,
+                mime: "application/pdf",
+                links: 0,
+                anchors: Vec::new(),
+            })
+        }
+    }
+}
+
+#[tracing::instrument(skip_all, fields(url = %document.url))]
+async fn index(document: Document, force: bool, state: &IndexState) -> anyhow::Result<Outcome> {
+    let Document {
+        url,
+        format,
+        title: fallback_title,
+        content,
+    } = document;
+
+    let hash = sha256::digest(&content);
+    if !force && is_indexed(&url, &hash, state).await? {
+        tracing::debug!(url = %url, "Skipping unchanged document");
+        return Ok(Outcome::Skipped);
+    }
+
+    let mut page = {
+        let url = url.clone();
+        tokio::task::spawn_blocking(move || extract_document(&url, format, content))
+            .await
+            .context("Extraction task")??
+    };
+    page.title = page.title.or(fallback_title);
+    store(&url, page, &hash, None, state).await?;
+    Ok(Outcome::Indexed)
+}
+
+async fn run(args: Args) {
+    let state = IndexState::new(
+        &args.mongo_uri_write,
+        &args.qdrant_uri_write,
+        EmbedderConfig {
+            kind: args.embedder,
+            tei_uri: args.tei_uri.clone(),
+            batch_window: args.embedder_batch_window_ms.map(Duration::from_millis),
+            model_dir: args.embedder_model_dir.clone(),
+            dimension: args.vector_dim,
+        },
+        SparseEmbedderConfig {
+            kind: args.sparse_embedder,
+            tei_uri: args.tei_sparse_uri.clone(),
+        },
+        args.redis_uri
+            .as_deref()
+            .map(|uri| redis::Client::open(uri).expect("Invalid Redis URI")),
+    )
+    .await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(args.batch_size);
+    let inputs = args.inputs.clone();
+    let base_url = args.base_url.clone();
+    let reader = tokio::task::spawn_blocking(move || {
+        for input in &inputs {
+            tracing::info!(input = %input.display(), "Reading input");
+            if let Err(e) = source::read(input, base_url.as_ref(), &tx) {
+                tracing::error!(input = %input.display(), "Failed to read input: {e:#}");
+            }
+        }
+    });
+
+    let force = args.force;
+    let instant = Instant::now();
+    let mut progress = Progress::default();
+    let mut batch = Vec::with_capacity(args.batch_size);
+    while rx.recv_many(&mut batch, args.batch_size).await > 0 {
+        let outcomes: Vec<_> = futures::stream::iter(batch.drain(..).map(|document| {
+            let url = document.url.clone();
+            let state = &state;
+            async move { (url, index(document, force, state).await) }
+        }))
+        .buffer_unordered(args.concurrency)
+        .collect()
+        .await;
+
+        for (url, outcome) in outcomes {
+            match outcome {
+                Ok(Outcome::Indexed) => progress.indexed += 1,
+                Ok(Outcome::Skipped) => progress.skipped += 1,
+                Err(e) => {
+                    tracing::error!(url = %url, "Failed to index document: {e:#}");
+                    progress.failed += 1;
+                }
+            }
+        }
+
+        let total = progress.total();
+        tracing::info!(
+            indexed = progress.indexed,
+            skipped = progress.skipped,
+            failed = progress.failed,
+            "Processed {total} documents ({:.1}/s)",
+            total as f64 / instant.elapsed().as_secs_f64()
+        );
+    }
+
+    if let Err(e) = reader.await {
+        tracing::error!(error = %e, "Reader task failed");
+    }
+
+    tracing::info!(
+        indexed = progress.indexed,
+        skipped = progress.skipped,
+        failed = progress.failed,
+        "Finished in {:.2}s",
+        instant.elapsed().as_secs_f64()
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    utils::start("indexer", Box::pin(run(args))).await;
+}
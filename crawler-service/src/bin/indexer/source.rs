@@ -0,0 +1,303 @@
+use std::{
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use tokio::sync::mpsc::Sender;
+use url::Url;
+use walkdir::WalkDir;
+use warc::{RecordType, WarcHeader, WarcReader};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Html,
+    Markdown,
+    Pdf,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "html" | "htm" | "xhtml" => Some(Format::Html),
+            "md" | "markdown" => Some(Format::Markdown),
+            "pdf" => Some(Format::Pdf),
+            _ => None,
+        }
+    }
+
+    fn from_content_type(value: &str) -> Option<Self> {
+        let mime: mime::Mime = value.parse().ok()?;
+        match mime.essence_str() {
+            "text/html" | "application/xhtml+xml" => Some(Format::Html),
+            "text/markdown" => Some(Format::Markdown),
+            "application/pdf" => Some(Format::Pdf),
+            _ => None,
+        }
+    }
+}
+
+pub struct Document {
+    pub url: Url,
+    pub format: Format,
+    /// Title to fall back to when none can be extracted from the content.
+    pub title: Option<String>,
+    pub content: Vec<u8>,
+}
+
+/// Sends every document found in `input` to `tx`.
+///
+/// `input` may be a single HTML, Markdown or PDF file, a directory of them, or a
+/// (optionally gzipped) WARC file. Reading stops once the receiver is dropped.
+pub fn read(input: &Path, base_url: Option<&Url>, tx: &Sender<Document>) -> anyhow::Result<()> {
+    if input.is_dir() {
+        read_dir(input, base_url, tx)
+    } else if Format::from_path(input).is_some() {
+        let root = input.parent().unwrap_or(input);
+        if let Some(document) = read_file(root, input, base_url)? {
+            let _ = tx.blocking_send(document);
+        }
+        Ok(())
+    } else if input.extension().is_some_and(|extension| extension == "gz") {
+        let reader = WarcReader::from_path_gzip(input)
+            .with_context(|| format!("Failed to open {}", input.display()))?;
+        read_warc(reader, tx)
+    } else {
+        let reader = WarcReader::from_path(input)
+            .with_context(|| format!("Failed to open {}", input.display()))?;
+        read_warc(reader, tx)
+    }
+}
+
+fn read_dir(root: &Path, base_url: Option<&Url>, tx: &Sender<Document>) -> anyhow::Result<()> {
+    for entry in WalkDir::new(root).follow_links(true).sort_by_file_name() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read directory entry");
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        match read_file(root, entry.path(), base_url) {
+            Ok(Some(document)) => {
+                if tx.blocking_send(document).is_err() {
+                    return Ok(());
+                }
+            }
+            Ok(None) => (),
+            Err(e) => tracing::warn!(path = %entry.path().display(), "Failed to read file: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+fn read_file(root: &Path, path: &Path, base_url: Option<&Url>) -> anyhow::Result<Option<Document>> {
+    let Some(format) = Format::from_path(path) else {
+        tracing::debug!(path = %path.display(), "Skipping unsupported file");
+        return Ok(None);
+    };
+    let url = file_url(root, path, base_url)?;
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned());
+    Ok(Some(Document {
+        url,
+        format,
+        title,
+        content,
+    }))
+}
+
+/// Maps `path` onto `base_url` when given, otherwise onto a `file://` URL.
+fn file_url(root: &Path, path: &Path, base_url: Option<&Url>) -> anyhow::Result<Url> {
+    match base_url {
+        Some(base_url) => {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let relative: Vec<_> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            base_url
+                .join(&relative.join("/"))
+                .with_context(|| format!("Failed to join {} onto {base_url}", path.display()))
+        }
+        None => {
+            let absolute: PathBuf = path
+                .canonicalize()
+                .with_context(|| format!("Failed to canonicalize {}", path.display()))?;
+            Url::from_file_path(&absolute)
+                .map_err(|_| anyhow::anyhow!("Invalid file path {}", absolute.display()))
+        }
+    }
+}
+
+fn read_warc<R: BufRead>(reader: WarcReader<R>, tx: &Sender<Document>) -> anyhow::Result<()> {
+    for record in reader.iter_records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read WARC record");
+                continue;
+            }
+        };
+        if *record.warc_type() != RecordType::Response {
+            continue;
+        }
+        let Some(target) = record.header(WarcHeader::TargetURI) else {
+            continue;
+        };
+        let url = match Url::parse(target.trim_matches(|c| c == '<' || c == '>')) {
+            Ok(url) => url,
+            Err(e) => {
+                tracing::warn!(error = %e, target = %target, "Invalid WARC target URI");
+                continue;
+            }
+        };
+        let Some(response) = HttpResponse::parse(record.body()) else {
+            tracing::debug!(url = %url, "Skipping unparsable HTTP response");
+            continue;
+        };
+        if response.status != 200 {
+            tracing::debug!(url = %url, status = response.status, "Skipping non-200 response");
+            continue;
+        }
+        let Some(format) = response.content_type.and_then(Format::from_content_type) else {
+            tracing::debug!(url = %url, content_type = ?response.content_type, "Skipping unsupported content");
+            continue;
+        };
+        let document = Document {
+            url,
+            format,
+            title: None,
+            content: response.body.to_vec(),
+        };
+        if tx.blocking_send(document).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct HttpResponse<'a> {
+    status: u16,
+    content_type: Option<&'a str>,
+    body: &'a [u8],
+}
+
+impl<'a> HttpResponse<'a> {
+    /// Splits the block of a WARC `response` record into status, `Content-Type` and body.
+    ///
+    /// Responses stored with a content or transfer encoding are rejected, as their
+    /// body is not the document itself.
+    fn parse(block: &'a [u8]) -> Option<Self> {
+        let end = block.windows(4).position(|window| window == b"\r\n\r\n")?;
+        let head = std::str::from_utf8(&block[..end]).ok()?;
+        let mut lines = head.split("\r\n");
+        let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+        let mut content_type = None;
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value);
+            } else if (name.eq_ignore_ascii_case("content-encoding")
+                || name.eq_ignore_ascii_case("transfer-encoding"))
+                && !value.eq_ignore_ascii_case("identity")
+            {
+                return None;
+            }
+        }
+        Some(Self {
+            status,
+            content_type,
+            body: &block[end + 4..],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response() {
+        let block = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 13\r\n\r\n<p>Hello</p>\n";
+        let response = HttpResponse::parse(block).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, Some("text/html; charset=utf-8"));
+        assert_eq!(response.body, b"<p>Hello</p>\n");
+        assert!(matches!(
+            response.content_type.and_then(Format::from_content_type),
+            Some(Format::Html)
+        ));
+    }
+
+    #[test]
+    fn non_200_status() {
+        let block = b"HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\n\r\nMissing";
+        let response = HttpResponse::parse(block).unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.body, b"Missing");
+    }
+
+    #[test]
+    fn header_names_are_case_insensitive() {
+        let block = b"HTTP/1.0 200 OK\r\ncontent-type: application/pdf\r\nX-Other\r\n\r\n%PDF";
+        let response = HttpResponse::parse(block).unwrap();
+        assert_eq!(response.content_type, Some("application/pdf"));
+        assert_eq!(response.body, b"%PDF");
+    }
+
+    #[test]
+    fn missing_content_type() {
+        let response = HttpResponse::parse(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+        assert_eq!(response.content_type, None);
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn encoded_bodies_are_rejected() {
+        let chunked =
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\n\r\n";
+        assert!(HttpResponse::parse(chunked).is_none());
+        let gzip = b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\n\x1f\x8b";
+        assert!(HttpResponse::parse(gzip).is_none());
+        let identity = b"HTTP/1.1 200 OK\r\nContent-Encoding: identity\r\n\r\nHello";
+        assert_eq!(HttpResponse::parse(identity).unwrap().body, b"Hello");
+    }
+
+    #[test]
+    fn malformed_head() {
+        // No blank line between the head and the body.
+        assert!(HttpResponse::parse(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n").is_none());
+        assert!(
+            HttpResponse::parse(b"HTTP/1.1 200 OK\nContent-Type: text/html\n\nHello").is_none()
+        );
+        assert!(HttpResponse::parse(b"HTTP/1.1\r\n\r\nHello").is_none());
+        assert!(HttpResponse::parse(b"HTTP/1.1 OK 200\r\n\r\nHello").is_none());
+        assert!(HttpResponse::parse(b"").is_none());
+    }
+
+    #[test]
+    fn content_types() {
+        let format = Format::from_content_type;
+        assert!(matches!(
+            format("application/xhtml+xml"),
+            Some(Format::Html)
+        ));
+        assert!(matches!(
+            format("text/markdown; charset=utf-8"),
+            Some(Format::Markdown)
+        ));
+        assert!(format("image/png").is_none());
+        assert!(format("not a type").is_none());
+    }
+}
@@ -1,42 +1,84 @@
 use crate::{
+    job::{self, in_scope},
     log::{Content, Log},
-    proto::{EmbedRequest, EmbedResponse},
-    state::AppState,
+    state::{AppState, IndexState},
     traverse::HtmlTraverse,
 };
 use anyhow::Context;
 use ego_tree::iter::Edge;
-use lapin::{options::QueueDeclareOptions, types::FieldTable, BasicProperties};
-use mongodb::bson::{doc, Uuid};
+use lapin::{
+    options::QueueDeclareOptions,
+    types::{AMQPValue, FieldTable},
+    BasicProperties,
+};
+use mongodb::bson::{doc, Bson, Uuid};
 use mongodm::{
     f,
     mongo::options::ReturnDocument,
-    operator::{Set, SetOnInsert},
+    operator::{AddToSet, Set, SetOnInsert, Unset},
     ToRepository,
 };
-use qdrant_client::qdrant::{value::Kind, PointStruct, UpsertPointsBuilder, Value};
+use qdrant_client::qdrant::{
+    value::Kind, DeletePointsBuilder, ListValue, PointStruct, PointsIdsList, UpsertPointsBuilder,
+    Value, Vector,
+};
+use redis::AsyncCommands;
 use scraper::{Html, Node, Selector};
 use std::collections::{HashMap, HashSet};
 use url::Url;
-use utils::database::{Page, UuidProjection, COLLNAME, DATABASE};
+use utils::database::{
+    FirstSeenProjection, Job, Metadata, Page, Status, UuidProjection, COLLNAME, DATABASE,
+    SPARSE_VECTOR,
+};
+use utils::redis::Key;
+
+/// Maximum number of characters of the body stored with the embeddings, for reranking.
+const PASSAGE_LENGTH: usize = 512;
+/// Maximum number of characters of a snippet window.
+const SNIPPET_LENGTH: usize = 240;
+/// Maximum number of snippet windows stored with the embeddings.
+const MAX_SNIPPETS: usize = 64;
+/// Maximum number of characters of a stored anchor text.
+const ANCHOR_LENGTH: usize = 80;
+/// Maximum number of anchor texts stored per page.
+const MAX_ANCHORS: usize = 64;
+
+/// AMQP header carrying the id of the job that discovered a URL.
+pub const JOB_HEADER: &str = "job";
 
 lazy_static::lazy_static! {
     static ref WHITESPACES: regex::Regex = regex::Regex::new(r"(\s)\s+").unwrap();
+    static ref SENTENCE_END: regex::Regex = regex::Regex::new(r"[.!?]\s+").unwrap();
     static ref HREF_SELECTOR: Selector = Selector::parse("a[href]").unwrap();
     static ref TITLE_SELECTOR: Selector = Selector::parse("title").unwrap();
+    static ref LANG_SELECTOR: Selector = Selector::parse("html[lang]").unwrap();
 }
 
-#[tracing::instrument(skip(log, state), fields(url = %url))]
-pub async fn process(url: &Url, log: &mut Log<'_>, state: &AppState) -> anyhow::Result<()> {
-    let content = if let Some(content) = get_content(url, state).await? {
-        content
-    } else {
-        tracing::debug!(url = %url, "Skipping URL due to empty content");
-        return Ok(());
-    };
-    tracing::debug!(content_length = content.len(), url = %url, "Retrieved content");
+/// Text, title, language and outgoing links extracted from a document.
+pub struct Extracted {
+    pub title: Option<String>,
+    pub body: String,
+    /// Primary language subtag declared by the document, e.g. `en` for `en-US`.
+    pub lang: Option<String>,
+    pub links: HashSet<Url>,
+    /// Distinct texts of the links, in document order.
+    pub anchors: Vec<String>,
+}
+
+/// What [`store`] indexes of a document.
+pub struct PageContent {
+    pub title: Option<String>,
+    pub body: String,
+    pub lang: Option<String>,
+    pub mime: &'static str,
+    /// Number of distinct outgoing links.
+    pub links: usize,
+    pub anchors: Vec<String>,
+}
 
-    let document = Html::parse_document(&content);
+/// Extracts the visible text, title, language and outgoing links of an HTML document.
+pub fn extract(url: &Url, content: &str) -> Extracted {
+    let document = Html::parse_document(content);
 
     let mut body = String::new();
     let traverse = HtmlTraverse::new(*document.root_element());
@@ -64,6 +106,129 @@ pub async fn process(url: &Url, log: &mut Log<'_>, state: &AppState) -> anyhow::
         .collect();
     tracing::debug!(links = links.len(), url = %url, "Extracted links");
 
+    let mut anchors: Vec<String> = Vec::new();
+    for element in document.select(&HREF_SELECTOR) {
+        let text = element.text().collect::<Vec<_>>().join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() || text.chars().count() > ANCHOR_LENGTH || anchors.contains(&text) {
+            continue;
+        }
+        anchors.push(text);
+        if anchors.len() == MAX_ANCHORS {
+            break;
+        }
+    }
+
+    let title = document
+        .select(&TITLE_SELECTOR)
+        .next()
+        .map(|element| element.inner_html());
+
+    let lang = document
+        .select(&LANG_SELECTOR)
+        .next()
+        .and_then(|element| element.value().attr("lang"))
+        .and_then(|lang| lang.split(['-', '_']).next())
+        .map(|lang| lang.trim().to_ascii_lowercase())
+        .filter(|lang| !lang.is_empty());
+
+    Extracted {
+        title,
+        body,
+        lang,
+        links,
+        anchors,
+    }
+}
+
+/// Leading words of `text`, up to `length` characters.
+fn truncate(text: &str, length: usize) -> &str {
+    let text = text.trim();
+    match text.char_indices().nth(length) {
+        Some((end, _)) => {
+            let cut = &text[..end];
+            cut.rfind(char::is_whitespace)
+                .map_or(cut, |space| cut[..space].trim_end())
+        }
+        None => text,
+    }
+}
+
+/// Splits `body` into windows of whole sentences of up to [`SNIPPET_LENGTH`] characters,
+/// which the backend picks result snippets from.
+///
+/// Sentences longer than a window are truncated, and only the first [`MAX_SNIPPETS`]
+/// windows are kept.
+fn passages(body: &str) -> Vec<String> {
+    let mut passages = Vec::new();
+    let mut window = String::new();
+    let mut start = 0;
+    let ends = SENTENCE_END
+        .find_iter(body)
+        .map(|m| m.end())
+        .chain(std::iter::once(body.len()));
+    for end in ends {
+        let sentence = truncate(&body[start..end], SNIPPET_LENGTH);
+        start = end;
+        if sentence.is_empty() {
+            continue;
+        }
+        let length = window.chars().count() + sentence.chars().count();
+        if !window.is_empty() && length >= SNIPPET_LENGTH {
+            passages.push(std::mem::take(&mut window));
+            if passages.len() == MAX_SNIPPETS {
+                return passages;
+            }
+        }
+        if !window.is_empty() {
+            window.push(' ');
+        }
+        window.push_str(sentence);
+    }
+    if !window.is_empty() {
+        passages.push(window);
+    }
+    passages
+}
+
+/// Result of a successful crawl.
+pub struct Crawled {
+    pub status: Status,
+    /// Number of discovered links published on the domain queues, already counted as
+    /// queued by the job.
+    pub queued: usize,
+}
+
+/// Fetches, extracts and stores `url`, then publishes its links.
+///
+/// Links of pages crawled on behalf of `job` are tagged with it and only published
+/// if they are within its scope.
+#[tracing::instrument(skip(log, state, job), fields(url = %url))]
+pub async fn process(
+    url: &Url,
+    log: &mut Log<'_>,
+    state: &AppState,
+    job: Option<&Job>,
+) -> anyhow::Result<Crawled> {
+    let content = if let Some(content) = get_content(url, state).await? {
+        content
+    } else {
+        tracing::debug!(url = %url, "Skipping URL due to empty content");
+        return Ok(Crawled {
+            status: Status::Empty,
+            queued: 0,
+        });
+    };
+    tracing::debug!(content_length = content.len(), url = %url, "Retrieved content");
+
+    let Extracted {
+        title,
+        body,
+        lang,
+        links,
+        anchors,
+    } = extract(url, &content);
+
     log.data = Some(Content {
         content_length: content.len(),
         body_length: body.len(),
@@ -71,19 +236,104 @@ pub async fn process(url: &Url, log: &mut Log<'_>, state: &AppState) -> anyhow::
     });
 
     let hash = sha256::digest(&content);
+    let job_uuid = job.map(|job| job.uuid);
+    let content = PageContent {
+        title,
+        body,
+        lang,
+        mime: "text/html",
+        links: links.len(),
+        anchors,
+    };
+    let status = store(url, content, &hash, job_uuid, &state.index).await?;
+
+    tracing::debug!(url = %url, "Publishing links");
+    let links: Vec<_> = links
+        .iter()
+        .filter(|link| job.map_or(true, |job| in_scope(job, link)))
+        .filter_map(|link| link.domain().map(|domain| (domain, link)))
+        .collect();
+    // Links are counted before they are published, a link crawled before the count
+    // would otherwise complete the job while the others are being published.
+    if let (Some(job), false) = (job, links.is_empty()) {
+        job::progress(job.uuid, 0, 0, links.len() as i64, state).await?;
+    }
+    let results = futures::future::join_all(
+        links
+            .iter()
+            .map(|(domain, link)| publish(domain, link, job_uuid, &state.amqp_channel)),
+    )
+    .await;
+    let failed = results.iter().filter(|result| result.is_err()).count();
+    if let (Some(job), true) = (job, failed > 0) {
+        job::progress(job.uuid, 0, 0, -(failed as i64), state).await?;
+    }
+    results
+        .into_iter()
+        .collect::<Result<(), _>>()
+        .context("Link publishing")?;
+    Ok(Crawled {
+        status,
+        queued: links.len(),
+    })
+}
+
+/// Upserts the `Page` record of `url` and, for non-empty bodies, its dense and sparse
+/// embeddings with the payload searches are filtered by.
+///
+/// Fails if the embeddings cannot be computed or written, after recording the page as
+/// failed so that it is crawled again.
+#[tracing::instrument(skip(content, hash, state), fields(url = %url))]
+pub async fn store(
+    url: &Url,
+    content: PageContent,
+    hash: &str,
+    job: Option<Uuid>,
+    state: &IndexState,
+) -> anyhow::Result<Status> {
+    let PageContent {
+        title,
+        body,
+        lang,
+        mime,
+        links,
+        anchors,
+    } = content;
+    let metadata = Metadata {
+        title: title.clone(),
+        lang: lang.clone(),
+        mime: Some(mime.to_string()),
+    };
+    let metadata = mongodm::bson::to_bson(&metadata).context("Failed to serialize metadata")?;
     let mut uuid = Uuid::new();
     let t = chrono::Utc::now();
+    let mut first = t;
+    let status = if body.is_empty() {
+        Status::Empty
+    } else {
+        Status::Indexed
+    };
     let filter = doc! { f!(url in Page): url.as_str() };
-    let update = doc! {
+    let mut update = doc! {
         SetOnInsert: {
             f!(first in Page): mongodm::bson::Bson::DateTime(t.into()),
             f!(uuid in Page): uuid,
         },
         Set: {
-            f!(sha256 in Page): &hash,
-            f!(last in Page): mongodm::bson::Bson::DateTime(t.into())
+            f!(sha256 in Page): hash,
+            f!(last in Page): mongodm::bson::Bson::DateTime(t.into()),
+            f!(status in Page): status,
+            f!(links in Page): links as i64,
+            f!(metadata in Page): metadata,
+            f!(anchors in Page): anchors,
+        },
+        Unset: {
+            f!(error in Page): "",
         }
     };
+    if let Some(job) = job {
+        update.insert(AddToSet, doc! { f!(jobs in Page): job });
+    }
     if body.is_empty() {
         let options = mongodm::mongo::options::UpdateOptions::builder()
             .upsert(true)
@@ -105,90 +355,255 @@ pub async fn process(url: &Url, log: &mut Log<'_>, state: &AppState) -> anyhow::
     } else {
         let options = mongodm::mongo::options::FindOneAndUpdateOptions::builder()
             .return_document(ReturnDocument::After)
-            .projection(doc! { f!(uuid in Page): 1 })
+            .projection(doc! { f!(uuid in Page): 1, f!(first in Page): 1 })
             .upsert(true)
             .build();
 
         if let Some(document) = state
             .mongo_client
             .database(DATABASE)
-            .repository::<UuidProjection>()
+            .repository::<FirstSeenProjection>()
             .find_one_and_update(filter, update)
             .with_options(options)
             .await
             .context("Failed to update or insert document")?
         {
             tracing::debug!(uuid = ?document.uuid, url = %url, "Updated document");
-            uuid = document.uuid
+            uuid = document.uuid;
+            first = document.first;
         } else {
             tracing::debug!(uuid = ?uuid, url = %url, "Inserted document");
         }
 
-        let request = EmbedRequest {
-            inputs: body,
-            truncate: true,
-            normalize: true,
-            truncation_direction: 0,
-            prompt_name: None,
-        };
-        match state
-            .tei_client
-            .clone()
-            .embed(request)
-            .await
-            .map(|r| r.into_inner())
-        {
-            Ok(EmbedResponse { embeddings, .. }) => {
-                let title = document
-                    .select(&TITLE_SELECTOR)
-                    .next()
-                    .map(|element| element.inner_html());
-                let mut payload = HashMap::new();
-
-                macro_rules! value {
-                    ($value:expr) => {
-                        Value {
-                            kind: Some(Kind::StringValue($value)),
-                        }
-                    };
+        let passage = truncate(&body, PASSAGE_LENGTH).to_string();
+        let passages = passages(&body);
+        let (embeddings, sparse) = tokio::join!(
+            state.embedder.embed(body.clone()),
+            state.sparse_embedder.embed_document(body)
+        );
+        let written = async {
+            let embeddings = embeddings.context("Failed to embed content")?;
+            // The dense vector is the unnamed default vector of the collection.
+            let mut vectors = HashMap::from([(String::new(), Vector::from(embeddings))]);
+            match sparse {
+                Ok(sparse) if !sparse.is_empty() => {
+                    vectors.insert(
+                        SPARSE_VECTOR.to_string(),
+                        Vector::new_sparse(sparse.indices, sparse.values),
+                    );
                 }
+                Ok(_) => {}
+                Err(e) => tracing::error!(url = %url, "Failed to embed sparse content: {e:#}"),
+            }
 
-                if let Some(title) = title {
-                    payload.insert("title", value!(title));
-                }
-                payload.insert("url", value!(url.to_string()));
-                let point = PointStruct::new(uuid.to_string(), embeddings, payload);
-                let request = UpsertPointsBuilder::new(COLLNAME, vec![point]);
-                match state.qdrant_client.upsert_points(request).await {
-                    Ok(info) => {
-                        tracing::debug!(operation_id = info.result.map(|r| r.operation_id), url = %url, "Upserted embeddings")
-                    }
-                    Err(e) => {
-                        tracing::error!(error = %e, url = %url, "Failed to upsert embeddings")
+            let mut payload = HashMap::new();
+
+            macro_rules! value {
+                ($value:expr) => {
+                    Value {
+                        kind: Some(Kind::StringValue($value)),
                     }
-                }
+                };
+            }
+
+            if let Some(title) = title {
+                payload.insert("title", value!(title));
             }
-            Err(e) => tracing::error!(error = %e, url = %url, "Failed to embed content"),
+            payload.insert("url", value!(url.to_string()));
+            payload.insert("passage", value!(passage));
+            if let Some(domain) = url.domain() {
+                payload.insert("domain", value!(domain.to_string()));
+            }
+            if let Some(lang) = lang {
+                payload.insert("lang", value!(lang));
+            }
+            payload.insert("mime", value!(mime.to_string()));
+            payload.insert("first", value!(first.to_rfc3339()));
+            payload.insert("last", value!(t.to_rfc3339()));
+            payload.insert(
+                "passages",
+                Value {
+                    kind: Some(Kind::ListValue(ListValue {
+                        values: passages.into_iter().map(|p| value!(p)).collect(),
+                    })),
+                },
+            );
+            let point = PointStruct::new(uuid.to_string(), vectors, payload);
+            let request = UpsertPointsBuilder::new(COLLNAME, vec![point]);
+            let info = state
+                .qdrant_client
+                .upsert_points(request)
+                .await
+                .context("Failed to upsert embeddings")?;
+            tracing::debug!(operation_id = info.result.map(|r| r.operation_id), url = %url, "Upserted embeddings");
+            count_change(state, 1).await;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        if let Err(e) = written {
+            // Without its point the page is not indexed, and clearing its digest keeps a
+            // crawl of the same content from being skipped as unchanged.
+            tracing::error!(url = %url, "Failed to index content: {e:#}");
+            let update = doc! {
+                Set: {
+                    f!(status in Page): Status::Failed,
+                    f!(error in Page): format!("{e:#}"),
+                    f!(sha256 in Page): "",
+                },
+            };
+            if let Err(e) = state
+                .mongo_client
+                .database(DATABASE)
+                .repository::<Page>()
+                .update_one(doc! { f!(url in Page): url.as_str() }, update)
+                .await
+            {
+                tracing::error!(url = %url, "Failed to record indexing failure: {e:#}");
+            }
+            return Err(e);
         }
     }
+    Ok(status)
+}
 
-    tracing::debug!(url = %url, "Publishing links");
-    let tasks = links.iter().filter_map(|link| {
-        if let Some(domain) = link.domain() {
-            Some(publish(domain, link, &state.amqp_channel))
-        } else {
-            None
-        }
-    });
-    futures::future::join_all(tasks)
+/// Counts `count` changes of the `pages` collection, which expire the search results
+/// cached by the backend once enough changes accumulate.
+async fn count_change(state: &IndexState, count: u64) {
+    let Some(redis_client) = &state.redis_client else {
+        return;
+    };
+    let result = async {
+        let mut conn = redis_client.get_multiplexed_tokio_connection().await?;
+        conn.incr::<_, _, ()>(Key::IndexChanges, count).await
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("Failed to count index change: {e:#}");
+    }
+}
+
+/// Records the outcome of a crawl that did not reach [`store`] on an already known page.
+pub async fn record_status(
+    url: &Url,
+    status: Status,
+    error: Option<String>,
+    state: &IndexState,
+) -> anyhow::Result<()> {
+    let filter = doc! { f!(url in Page): url.as_str() };
+    let update = match error {
+        Some(error) => doc! { Set: { f!(status in Page): status, f!(error in Page): error } },
+        None => doc! { Set: { f!(status in Page): status }, Unset: { f!(error in Page): "" } },
+    };
+    let result = state
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Page>()
+        .update_one(filter, update)
         .await
-        .into_iter()
-        .collect::<Result<(), _>>()
-        .context("Link publishing")
+        .context("Failed to update document status")?;
+    tracing::debug!(matched_count = result.matched_count, status = ?status, url = %url, "Recorded status");
+    Ok(())
+}
+
+/// Removes the page of `url` from MongoDB and its point from Qdrant.
+///
+/// Returns `false` if the page is not known.
+#[tracing::instrument(skip(state), fields(url = %url))]
+pub async fn delete(url: &Url, state: &IndexState) -> anyhow::Result<bool> {
+    let filter = doc! { f!(url in Page): url.as_str() };
+    let options = mongodm::mongo::options::FindOneOptions::builder()
+        .projection(doc! { f!(uuid in Page): 1 })
+        .build();
+    let repository = state
+        .mongo_client
+        .database(DATABASE)
+        .repository::<UuidProjection>();
+    let Some(UuidProjection { uuid }) = repository
+        .find_one(filter.clone())
+        .with_options(options)
+        .await
+        .context("Failed to find document")?
+    else {
+        return Ok(false);
+    };
+
+    let request = DeletePointsBuilder::new(COLLNAME)
+        .points(PointsIdsList {
+            ids: vec![uuid.to_string().into()],
+        })
+        .wait(true);
+    state
+        .qdrant_client
+        .delete_points(request)
+        .await
+        .context("Failed to delete point")?;
+    tracing::debug!(uuid = ?uuid, url = %url, "Deleted point");
+    count_change(state, 1).await;
+
+    repository
+        .delete_one(filter)
+        .await
+        .context("Failed to delete document")?;
+    tracing::debug!(uuid = ?uuid, url = %url, "Deleted document");
+    Ok(true)
+}
+
+/// Number of points deleted from Qdrant per request by [`delete_prefix`].
+const DELETE_BATCH: usize = 1000;
+
+/// Removes the pages whose URL starts with `prefix` from MongoDB and their points from
+/// Qdrant.
+///
+/// A parsed URL always has a path, so a prefix cannot end within a host or port: the
+/// prefix `https://example.com` is `https://example.com/`, which does not match
+/// `https://example.com.evil.org/` nor `https://example.com:8443/`.
+///
+/// Returns the number of pages deleted.
+#[tracing::instrument(skip(state), fields(prefix = %prefix))]
+pub async fn delete_prefix(prefix: &Url, state: &IndexState) -> anyhow::Result<u64> {
+    let pattern = format!("^{}", regex::escape(prefix.as_str()));
+    let filter = doc! { f!(url in Page): { "$regex": pattern } };
+    let repository = state
+        .mongo_client
+        .database(DATABASE)
+        .repository::<UuidProjection>();
+    let mut cursor = repository
+        .find(filter.clone())
+        .projection(doc! { f!(uuid in Page): 1 })
+        .await
+        .context("Failed to find documents")?;
+    let mut uuids = Vec::new();
+    while cursor.advance().await.context("Failed to read documents")? {
+        let UuidProjection { uuid } = cursor.deserialize_current()?;
+        uuids.push(uuid);
+    }
+
+    for batch in uuids.chunks(DELETE_BATCH) {
+        let request = DeletePointsBuilder::new(COLLNAME)
+            .points(PointsIdsList {
+                ids: batch.iter().map(|uuid| uuid.to_string().into()).collect(),
+            })
+            .wait(true);
+        state
+            .qdrant_client
+            .delete_points(request)
+            .await
+            .context("Failed to delete points")?;
+        count_change(state, batch.len() as u64).await;
+    }
+    tracing::debug!(points = uuids.len(), "Deleted points");
+
+    let uuids: Vec<_> = uuids.into_iter().map(Bson::from).collect();
+    let result = repository
+        .delete_many(doc! { f!(uuid in Page): { "$in": uuids } })
+        .await
+        .context("Failed to delete documents")?;
+    tracing::debug!(documents = result.deleted_count, "Deleted documents");
+    Ok(result.deleted_count)
 }
 
 #[tracing::instrument(skip(state), fields(url = %url.as_str()))]
-async fn get_content(url: &url::Url, state: &AppState) -> anyhow::Result<Option<String>> {
+pub async fn get_content(url: &url::Url, state: &AppState) -> anyhow::Result<Option<String>> {
     tracing::debug!(url = %url, "Sending GET request");
     let response = state
         .reqwest_client
@@ -218,7 +633,13 @@ async fn get_content(url: &url::Url, state: &AppState) -> anyhow::Result<Option<
     response.text().await.map(Some).context("content")
 }
 
-async fn publish(domain: &str, url: &url::Url, channel: &lapin::Channel) -> anyhow::Result<()> {
+/// Publishes `url` on the queue of `domain`, tagged with the job that discovered it.
+pub async fn publish(
+    domain: &str,
+    url: &url::Url,
+    job: Option<Uuid>,
+    channel: &lapin::Channel,
+) -> anyhow::Result<()> {
     channel
         .queue_declare(
             domain,
@@ -230,7 +651,15 @@ async fn publish(domain: &str, url: &url::Url, channel: &lapin::Channel) -> anyh
         )
         .await
         .context("queue declare")?;
-    let props = BasicProperties::default().with_delivery_mode(2);
+    let mut props = BasicProperties::default().with_delivery_mode(2);
+    if let Some(job) = job {
+        let mut headers = FieldTable::default();
+        headers.insert(
+            JOB_HEADER.into(),
+            AMQPValue::LongString(job.to_string().into()),
+        );
+        props = props.with_headers(headers);
+    }
     channel
         .basic_publish(
             "",
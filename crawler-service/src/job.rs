@@ -0,0 +1,402 @@
+use std::time::SystemTime;
+
+use anyhow::Context;
+use mongodb::bson::{doc, Uuid};
+use mongodm::{
+    f,
+    mongo::options::{FindOneAndUpdateOptions, ReturnDocument},
+    operator::{Expr, In, Inc, LesserThan, LesserThanEqual, Or, Set},
+    ToRepository,
+};
+use redis::AsyncCommands;
+use url::Url;
+use utils::{
+    database::{Job, JobState, Scope, DATABASE},
+    redis::Key,
+};
+
+use crate::{core::publish, proto, state::AppState};
+
+/// What to do with a URL that was queued on behalf of a job.
+pub enum Admission {
+    /// The URL counts against the budget of the job and can be crawled.
+    Crawl(Job),
+    /// The job is paused, the URL has to be [deferred](defer) until it resumes.
+    Defer,
+    /// The job is cancelled, completed or unknown.
+    Drop,
+}
+
+pub fn in_scope(job: &Job, url: &Url) -> bool {
+    match job.scope {
+        Scope::Domain => job.seeds.iter().any(|seed| {
+            Url::parse(seed)
+                .is_ok_and(|seed| seed.domain().is_some() && seed.domain() == url.domain())
+        }),
+        Scope::Prefix => job.seeds.iter().any(|seed| url.as_str().starts_with(seed)),
+        Scope::Any => true,
+    }
+}
+
+pub fn parse_id(id: &str) -> anyhow::Result<Uuid> {
+    Uuid::parse_str(id).with_context(|| format!("Invalid job id `{id}`"))
+}
+
+/// Persists a new running job and publishes its seeds.
+///
+/// Every seed must have a domain, as it is published on the queue of that domain. The
+/// job is cancelled if one of its seeds cannot be published, the seeds published
+/// before it are then dropped as they are taken off their queue.
+#[tracing::instrument(skip(seeds, state))]
+pub async fn create(
+    name: String,
+    seeds: Vec<Url>,
+    scope: Scope,
+    budget: i64,
+    state: &AppState,
+) -> anyhow::Result<Job> {
+    let t = chrono::Utc::now();
+    let job = Job {
+        uuid: Uuid::new(),
+        name,
+        seeds: seeds.iter().map(|seed| seed.to_string()).collect(),
+        scope,
+        budget,
+        state: JobState::Running,
+        fetched: 0,
+        indexed: 0,
+        failed: 0,
+        queued: seeds.len() as i64,
+        created: t,
+        updated: t,
+    };
+    state
+        .index
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Job>()
+        .insert_one(&job)
+        .await
+        .context("Failed to insert job")?;
+
+    for (published, seed) in seeds.iter().enumerate() {
+        let domain = seed
+            .domain()
+            .with_context(|| format!("Missing domain for {seed}"))?;
+        if let Err(e) = publish(domain, seed, Some(job.uuid), &state.amqp_channel).await {
+            let unpublished = (seeds.len() - published) as i64;
+            transition(job.uuid, &[JobState::Running], JobState::Cancelled, state).await?;
+            progress(job.uuid, 0, 0, -unpublished, state).await?;
+            return Err(e.context(format!(
+                "Cancelled job {} as a seed was not queued",
+                job.uuid
+            )));
+        }
+    }
+    tracing::info!(uuid = ?job.uuid, queued = seeds.len(), "Created job");
+    Ok(job)
+}
+
+pub async fn get(uuid: Uuid, state: &AppState) -> anyhow::Result<Option<Job>> {
+    state
+        .index
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Job>()
+        .find_one(doc! { f!(uuid in Job): uuid })
+        .await
+        .context("Failed to find job")
+}
+
+/// Moves the job to `to` if it currently is in one of `from`.
+///
+/// Returns the job as it is after the transition, or `None` if it does not exist.
+pub async fn transition(
+    uuid: Uuid,
+    from: &[JobState],
+    to: JobState,
+    state: &AppState,
+) -> anyhow::Result<Option<Job>> {
+    let filter = doc! {
+        f!(uuid in Job): uuid,
+        f!(state in Job): { In: from.to_vec() },
+    };
+    let update = doc! {
+        Set: {
+            f!(state in Job): to,
+            f!(updated in Job): mongodm::bson::Bson::DateTime(chrono::Utc::now().into()),
+        }
+    };
+    let options = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+    let repository = state
+        .index
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Job>();
+    match repository
+        .find_one_and_update(filter, update)
+        .with_options(options)
+        .await
+        .context("Failed to update job state")?
+    {
+        Some(job) => Ok(Some(job)),
+        None => get(uuid, state).await,
+    }
+}
+
+/// Claims one page of the budget of the job for a URL taken off a domain queue.
+///
+/// An admitted URL counts as queued until its crawl [progresses](progress) the job, so
+/// that the job is not completed while it is in flight.
+#[tracing::instrument(skip(state))]
+pub async fn admit(uuid: Uuid, state: &AppState) -> anyhow::Result<Admission> {
+    let repository = state
+        .index
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Job>();
+    let filter = doc! {
+        f!(uuid in Job): uuid,
+        f!(state in Job): JobState::Running,
+        Or: [
+            { f!(budget in Job): { LesserThanEqual: 0 } },
+            { Expr: { LesserThan: [format!("${}", f!(fetched in Job)), format!("${}", f!(budget in Job))] } },
+        ],
+    };
+    let update = doc! {
+        Inc: { f!(fetched in Job): 1 },
+        Set: { f!(updated in Job): mongodm::bson::Bson::DateTime(chrono::Utc::now().into()) },
+    };
+    let options = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+    if let Some(job) = repository
+        .find_one_and_update(filter, update)
+        .with_options(options)
+        .await
+        .context("Failed to admit URL")?
+    {
+        return Ok(Admission::Crawl(job));
+    }
+
+    let Some(job) = get(uuid, state).await? else {
+        tracing::warn!(uuid = ?uuid, "Unknown job");
+        return Ok(Admission::Drop);
+    };
+    match job.state {
+        JobState::Paused => Ok(Admission::Defer),
+        JobState::Running => {
+            tracing::info!(uuid = ?uuid, "Job exhausted its budget");
+            transition(uuid, &[JobState::Running], JobState::Completed, state).await?;
+            progress(uuid, 0, 0, -1, state).await?;
+            Ok(Admission::Drop)
+        }
+        JobState::Cancelled | JobState::Completed => {
+            progress(uuid, 0, 0, -1, state).await?;
+            Ok(Admission::Drop)
+        }
+    }
+}
+
+/// Gives back the page of the budget claimed by [`admit`] for a URL that was not
+/// fetched, as it was crawled recently or is disallowed by robots.txt.
+pub async fn refund(uuid: Uuid, state: &AppState) -> anyhow::Result<()> {
+    let update = doc! {
+        Inc: { f!(fetched in Job): -1 },
+        Set: { f!(updated in Job): mongodm::bson::Bson::DateTime(chrono::Utc::now().into()) },
+    };
+    state
+        .index
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Job>()
+        .update_one(doc! { f!(uuid in Job): uuid }, update)
+        .await
+        .context("Failed to refund job budget")?;
+    Ok(())
+}
+
+/// Sets a URL of a paused job aside until the job resumes or is cancelled.
+///
+/// The URL still counts as queued. It is released at once if the job left the paused
+/// state in the meantime, as its transition may have released the job already.
+pub async fn defer(uuid: Uuid, url: &Url, state: &AppState) -> anyhow::Result<()> {
+    let mut conn = state
+        .redis_client
+        .get_multiplexed_tokio_connection()
+        .await?;
+    let id = uuid.to_string();
+    conn.rpush::<_, _, ()>(Key::DeferredUrls(&id), url.as_str())
+        .await
+        .context("Failed to defer URL")?;
+    if get(uuid, state)
+        .await?
+        .is_some_and(|job| job.state != JobState::Paused)
+    {
+        release(uuid, state).await?;
+    }
+    Ok(())
+}
+
+/// Queues the deferred URLs of a running job again, or drops those of a job that is
+/// not paused anymore.
+pub async fn release(uuid: Uuid, state: &AppState) -> anyhow::Result<()> {
+    let Some(job) = get(uuid, state).await? else {
+        return Ok(());
+    };
+    let id = uuid.to_string();
+    let key = Key::DeferredUrls(&id);
+    let mut conn = state
+        .redis_client
+        .get_multiplexed_tokio_connection()
+        .await?;
+    match job.state {
+        JobState::Paused => Ok(()),
+        JobState::Running => {
+            let mut released = 0;
+            // URLs are popped one at a time so that concurrent releases share them.
+            while let Some(url) = conn
+                .lpop::<_, Option<String>>(&key, None)
+                .await
+                .context("Failed to pop deferred URL")?
+            {
+                let url = match Url::parse(&url) {
+                    Ok(url) => url,
+                    Err(e) => {
+                        tracing::warn!(url, "Dropping invalid deferred URL: {e}");
+                        progress(uuid, 0, 0, -1, state).await?;
+                        continue;
+                    }
+                };
+                let Some(domain) = url.domain() else {
+                    progress(uuid, 0, 0, -1, state).await?;
+                    continue;
+                };
+                if let Err(e) = publish(domain, &url, Some(uuid), &state.amqp_channel).await {
+                    conn.lpush::<_, _, ()>(&key, url.as_str()).await?;
+                    return Err(e);
+                }
+                released += 1;
+            }
+            tracing::info!(uuid = ?uuid, released, "Queued deferred URLs");
+            Ok(())
+        }
+        JobState::Cancelled | JobState::Completed => {
+            let (dropped,): (i64,) = redis::pipe()
+                .atomic()
+                .llen(&key)
+                .del(&key)
+                .ignore()
+                .query_async(&mut conn)
+                .await
+                .context("Failed to drop deferred URLs")?;
+            if dropped > 0 {
+                tracing::info!(uuid = ?uuid, dropped, "Dropped deferred URLs");
+                progress(uuid, 0, 0, -dropped, state).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Adds to the counters of the job and completes it once nothing is left queued.
+pub async fn progress(
+    uuid: Uuid,
+    indexed: i64,
+    failed: i64,
+    queued: i64,
+    state: &AppState,
+) -> anyhow::Result<Option<Job>> {
+    let repository = state
+        .index
+        .mongo_client
+        .database(DATABASE)
+        .repository::<Job>();
+    let t = mongodm::bson::Bson::DateTime(chrono::Utc::now().into());
+    let update = doc! {
+        Inc: {
+            f!(indexed in Job): indexed,
+            f!(failed in Job): failed,
+            f!(queued in Job): queued,
+        },
+        Set: { f!(updated in Job): t.clone() },
+    };
+    let options = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+    let job = repository
+        .find_one_and_update(doc! { f!(uuid in Job): uuid }, update)
+        .with_options(options)
+        .await
+        .context("Failed to update job counters")?;
+
+    if job
+        .as_ref()
+        .is_some_and(|job| job.state == JobState::Running && job.queued <= 0)
+    {
+        let filter = doc! {
+            f!(uuid in Job): uuid,
+            f!(state in Job): JobState::Running,
+            f!(queued in Job): { LesserThanEqual: 0 },
+        };
+        let update = doc! { Set: { f!(state in Job): JobState::Completed, f!(updated in Job): t } };
+        repository
+            .update_one(filter, update)
+            .await
+            .context("Failed to complete job")?;
+        return get(uuid, state).await;
+    }
+    Ok(job)
+}
+
+impl From<Scope> for proto::JobScope {
+    fn from(value: Scope) -> Self {
+        match value {
+            Scope::Domain => proto::JobScope::Domain,
+            Scope::Prefix => proto::JobScope::Prefix,
+            Scope::Any => proto::JobScope::Any,
+        }
+    }
+}
+
+impl From<proto::JobScope> for Scope {
+    fn from(value: proto::JobScope) -> Self {
+        match value {
+            proto::JobScope::Domain => Scope::Domain,
+            proto::JobScope::Prefix => Scope::Prefix,
+            proto::JobScope::Any => Scope::Any,
+        }
+    }
+}
+
+impl From<JobState> for proto::JobState {
+    fn from(value: JobState) -> Self {
+        match value {
+            JobState::Running => proto::JobState::Running,
+            JobState::Paused => proto::JobState::Paused,
+            JobState::Cancelled => proto::JobState::Cancelled,
+            JobState::Completed => proto::JobState::Completed,
+        }
+    }
+}
+
+impl From<Job> for proto::Job {
+    fn from(job: Job) -> Self {
+        Self {
+            id: job.uuid.to_string(),
+            name: job.name,
+            seeds: job.seeds,
+            scope: proto::JobScope::from(job.scope).into(),
+            budget: job.budget.max(0) as u64,
+            state: proto::JobState::from(job.state).into(),
+            fetched: job.fetched.max(0) as u64,
+            indexed: job.indexed.max(0) as u64,
+            failed: job.failed.max(0) as u64,
+            queued: job.queued.max(0) as u64,
+            created: Some(SystemTime::from(job.created).into()),
+            updated: Some(SystemTime::from(job.updated).into()),
+        }
+    }
+}
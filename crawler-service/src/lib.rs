@@ -0,0 +1,10 @@
+pub mod core;
+pub mod job;
+pub mod log;
+pub mod robots;
+pub mod state;
+pub mod traverse;
+
+pub mod proto {
+    tonic::include_proto!("crawler");
+}
@@ -1,100 +1,224 @@
-mod core;
-mod log;
-mod robots;
-mod state;
-mod traverse;
-
-use log::Log;
-use mongodb::{bson::doc, options::CountOptions};
-use mongodm::{f, prelude::GreaterThan, ToRepository};
-use proto::{
-    crawler_server::{Crawler, CrawlerServer},
-    CrawlRequest,
+use std::{pin::Pin, time::SystemTime};
+
+use crawler_service::{
+    core::{
+        delete, delete_prefix, extract, get_content, process, publish, record_status, Crawled,
+        Extracted,
+    },
+    job::{self, Admission},
+    log::Log,
+    proto::{
+        self,
+        crawler_server::{Crawler, CrawlerServer},
+        CrawlBatchRequest, CrawlBatchResponse, CrawlRequest, CrawlStatus, CreateJobRequest,
+        DeletePrefixResponse, InspectResponse, JobRequest, JobScope, JobState, PageRequest,
+        PageStatus, PrefixRequest, RejectedUrl,
+    },
+    robots::is_robots_allowed,
+    state::AppState,
 };
-use tokio::time::Instant;
+use mongodb::{
+    bson::{doc, Uuid},
+    options::CountOptions,
+};
+use mongodm::{f, prelude::GreaterThan, ToRepository};
+use tokio::time::{Duration, Instant};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
 use tonic::{transport::Server, Request, Response, Status};
-use utils::database::{Page, DATABASE};
+use url::Url;
+use utils::database::{self, Job, Page, DATABASE};
 
-use crate::{core::process, robots::is_robots_allowed, state::AppState};
-
-mod proto {
-    tonic::include_proto!("crawler");
-    tonic::include_proto!("tei.v1");
-}
+/// Interval at which `WatchJob` polls the job for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
 
 struct CrawlerService {
     state: AppState,
 }
 
-#[tonic::async_trait]
-impl Crawler for CrawlerService {
-    async fn crawl(&self, request: Request<CrawlRequest>) -> Result<Response<()>, Status> {
-        let instant = Instant::now();
+fn parse_url(url: &str) -> Result<Url, Status> {
+    Url::parse(url).map_err(|e| {
+        tracing::error!(error = %e, "Failed to parse URL");
+        Status::invalid_argument(e.to_string())
+    })
+}
 
-        let CrawlRequest { url } = request.into_inner();
-        let url = match reqwest::Url::parse(&url) {
-            Err(e) => {
-                tracing::error!(error = %e, "Failed to parse URL");
-                return Err(Status::invalid_argument(e.to_string()));
+fn crawl_status(status: database::Status) -> CrawlStatus {
+    match status {
+        database::Status::Indexed => CrawlStatus::Indexed,
+        database::Status::Empty => CrawlStatus::Empty,
+        database::Status::Disallowed => CrawlStatus::Disallowed,
+        database::Status::Failed => CrawlStatus::Failed,
+    }
+}
+
+fn parse_job_id(id: &str) -> Result<Uuid, Status> {
+    job::parse_id(id).map_err(|e| Status::invalid_argument(format!("{e:#}")))
+}
+
+fn job_response(job: anyhow::Result<Option<Job>>) -> Result<Response<proto::Job>, Status> {
+    match job {
+        Ok(Some(job)) => Ok(Response::new(job.into())),
+        Ok(None) => Err(Status::not_found("Job not found")),
+        Err(e) => {
+            tracing::error!("Failed to access job: {e:#}");
+            Err(Status::internal(e.to_string()))
+        }
+    }
+}
+
+impl CrawlerService {
+    /// Queues again or drops the URLs set aside while the job was paused.
+    async fn release_deferred(&self, uuid: Uuid, job: &anyhow::Result<Option<Job>>) {
+        if let Ok(Some(job)) = job {
+            if job.state != database::JobState::Paused {
+                if let Err(e) = job::release(uuid, &self.state).await {
+                    tracing::error!(uuid = ?uuid, "Failed to release deferred URLs: {e:#}");
+                }
             }
-            Ok(url) => url,
-        };
-        tracing::debug!(url = %url, "Received crawl request");
+        }
+    }
 
-        let (log, response) = match is_robots_allowed(&url, &self.state).await {
-            Ok(true) => {
-                tracing::debug!(url = %url, "robots.txt allows crawling");
-                let repo = self
-                    .state
-                    .mongo_client
-                    .database(DATABASE)
-                    .repository::<Page>();
-                let count_options = CountOptions::builder().limit(1).build();
-                let past = chrono::Utc::now() - chrono::Duration::hours(1);
-                let filter = doc! {
-                    f!(url in Page): url.as_str(),
-                    f!(last in Page): { GreaterThan: past }
-                };
-                match repo
-                    .count_documents(filter)
-                    .with_options(count_options)
-                    .await
-                {
-                    Ok(0) => (),
-                    Ok(_) => {
-                        tracing::debug!(url = %url, "URL already crawled within the last hour");
+    /// Crawls `url` and accounts for it in the progress of the job that discovered it.
+    async fn crawl_url(
+        &self,
+        url: Url,
+        force: bool,
+        job_id: Option<String>,
+    ) -> Result<Response<()>, Status> {
+        let job = match job_id {
+            Some(id) => {
+                let uuid = parse_job_id(&id)?;
+                match job::admit(uuid, &self.state).await {
+                    Ok(Admission::Crawl(job)) => Some(job),
+                    Ok(Admission::Defer) => {
+                        tracing::debug!(url = %url, job = %id, "Job paused, deferring URL");
+                        job::defer(uuid, &url, &self.state)
+                            .await
+                            .map_err(|e| Status::internal(format!("{e:#}")))?;
+                        return Ok(Response::new(()));
+                    }
+                    Ok(Admission::Drop) => {
+                        tracing::debug!(url = %url, job = %id, "Job not running, dropping URL");
                         return Ok(Response::new(()));
                     }
                     Err(e) => {
-                        tracing::error!(error = %e, url = %url, "Failed to check if URL was already crawled");
+                        tracing::error!(url = %url, job = %id, "Failed to admit URL: {e:#}");
                         return Err(Status::internal(e.to_string()));
                     }
                 }
-                let mut log = Log::from_url(&url, true);
-                match process(&url, &mut log, &self.state).await {
-                    Ok(_) => {
+            }
+            None => None,
+        };
+
+        let result = self.crawl_page(&url, force, job.as_ref()).await;
+
+        if let Some(job) = job {
+            let (indexed, failed) = match &result {
+                Ok(Some(crawled)) => ((crawled.status == database::Status::Indexed) as i64, 0),
+                Ok(None) => (0, 0),
+                Err(_) => (0, 1),
+            };
+            // The URL leaves the queue, the links it published were counted already.
+            if let Err(e) = job::progress(job.uuid, indexed, failed, -1, &self.state).await {
+                tracing::error!(url = %url, "Failed to record job progress: {e:#}");
+            }
+        }
+
+        result.map(|_| Response::new(()))
+    }
+
+    /// Crawls `url`, skipping it if it was crawled within the last hour unless `force` is set.
+    ///
+    /// Returns `None` if the page was skipped.
+    async fn crawl_page(
+        &self,
+        url: &Url,
+        force: bool,
+        job: Option<&Job>,
+    ) -> Result<Option<Crawled>, Status> {
+        let instant = Instant::now();
+        // Whether the page was fetched, the budget claimed for it is given back otherwise.
+        let mut fetched = false;
+
+        let (log, response) = match is_robots_allowed(url, &self.state).await {
+            Ok(true) => {
+                tracing::debug!(url = %url, "robots.txt allows crawling");
+                if !force {
+                    let repo = self
+                        .state
+                        .index
+                        .mongo_client
+                        .database(DATABASE)
+                        .repository::<Page>();
+                    let count_options = CountOptions::builder().limit(1).build();
+                    let past = chrono::Utc::now() - chrono::Duration::hours(1);
+                    let filter = doc! {
+                        f!(url in Page): url.as_str(),
+                        f!(last in Page): { GreaterThan: past }
+                    };
+                    match repo
+                        .count_documents(filter)
+                        .with_options(count_options)
+                        .await
+                    {
+                        Ok(0) => (),
+                        Ok(_) => {
+                            tracing::debug!(url = %url, "URL already crawled within the last hour");
+                            return Ok(None);
+                        }
+                        Err(e) => {
+                            tracing::error!(error = %e, url = %url, "Failed to check if URL was already crawled");
+                            return Err(Status::internal(e.to_string()));
+                        }
+                    }
+                }
+                let mut log = Log::from_url(url, true);
+                fetched = true;
+                match process(url, &mut log, &self.state, job).await {
+                    Ok(crawled) => {
                         tracing::debug!(url = %url, "Successfully crawled URL");
-                        (log, Ok(Response::new(())))
+                        (log, Ok(Some(crawled)))
                     }
                     Err(e) => {
                         tracing::error!(error = %e, url = %url, "Failed to process URL");
                         log.error = true;
+                        if let Err(e) = record_status(
+                            url,
+                            database::Status::Failed,
+                            Some(format!("{e:#}")),
+                            &self.state.index,
+                        )
+                        .await
+                        {
+                            tracing::error!(error = %e, url = %url, "Failed to record status");
+                        }
                         (log, Err(Status::internal(e.to_string())))
                     }
                 }
             }
             Ok(false) => {
                 tracing::debug!(url = %url, "Robots.txt disallows crawling");
-                (Log::from_url(&url, false), Ok(Response::new(())))
+                if let Err(e) =
+                    record_status(url, database::Status::Disallowed, None, &self.state.index).await
+                {
+                    tracing::error!(error = %e, url = %url, "Failed to record status");
+                }
+                (Log::from_url(url, false), Ok(None))
             }
             Err(e) => {
                 tracing::error!(error = %e, url = %url, "Failed to check robots.txt");
-                let mut log = Log::from_url(&url, false);
+                let mut log = Log::from_url(url, false);
                 log.error = true;
                 (log, Err(Status::internal(e.to_string())))
             }
         };
 
+        if let (Some(job), false) = (job, fetched) {
+            if let Err(e) = job::refund(job.uuid, &self.state).await {
+                tracing::error!(url = %url, job = %job.uuid, "Failed to refund budget: {e:#}");
+            }
+        }
+
         tracing::info!(url = %url, "Crawled in {:.2}ms", instant.elapsed().as_millis());
 
         let logstash_post = self
@@ -113,6 +237,323 @@ impl Crawler for CrawlerService {
     }
 }
 
+#[tonic::async_trait]
+impl Crawler for CrawlerService {
+    async fn crawl(&self, request: Request<CrawlRequest>) -> Result<Response<()>, Status> {
+        let CrawlRequest { url, job_id } = request.into_inner();
+        let url = parse_url(&url)?;
+        tracing::debug!(url = %url, "Received crawl request");
+        self.crawl_url(url, false, job_id).await
+    }
+
+    async fn crawl_batch(
+        &self,
+        request: Request<CrawlBatchRequest>,
+    ) -> Result<Response<CrawlBatchResponse>, Status> {
+        let CrawlBatchRequest { urls } = request.into_inner();
+        tracing::debug!(urls = urls.len(), "Received crawl batch request");
+
+        let mut queued = 0;
+        let mut rejected = Vec::new();
+        for url in urls {
+            let parsed = match Url::parse(&url) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    rejected.push(RejectedUrl {
+                        url,
+                        reason: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let Some(domain) = parsed.domain() else {
+                rejected.push(RejectedUrl {
+                    url,
+                    reason: "Missing domain".to_string(),
+                });
+                continue;
+            };
+            match publish(domain, &parsed, None, &self.state.amqp_channel).await {
+                Ok(()) => queued += 1,
+                Err(e) => {
+                    tracing::error!(url = %parsed, "Failed to publish URL: {e:#}");
+                    rejected.push(RejectedUrl {
+                        url,
+                        reason: format!("{e:#}"),
+                    });
+                }
+            }
+        }
+        tracing::info!(
+            queued = queued,
+            rejected = rejected.len(),
+            "Queued crawl batch"
+        );
+
+        Ok(Response::new(CrawlBatchResponse { queued, rejected }))
+    }
+
+    async fn get_page_status(
+        &self,
+        request: Request<PageRequest>,
+    ) -> Result<Response<PageStatus>, Status> {
+        let PageRequest { url } = request.into_inner();
+        let url = parse_url(&url)?;
+        let page = self
+            .state
+            .index
+            .mongo_client
+            .database(DATABASE)
+            .repository::<Page>()
+            .find_one(doc! { f!(url in Page): url.as_str() })
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, url = %url, "Failed to find page");
+                Status::internal(e.to_string())
+            })?
+            .ok_or_else(|| Status::not_found(format!("{url} has not been crawled")))?;
+
+        Ok(Response::new(PageStatus {
+            url: page.url,
+            uuid: page.uuid.to_string(),
+            first: Some(SystemTime::from(page.first).into()),
+            last: Some(SystemTime::from(page.last).into()),
+            sha256: page.sha256,
+            status: crawl_status(page.status).into(),
+            error: page.error,
+        }))
+    }
+
+    async fn recrawl(&self, request: Request<CrawlRequest>) -> Result<Response<()>, Status> {
+        let CrawlRequest { url, job_id } = request.into_inner();
+        let url = parse_url(&url)?;
+        tracing::debug!(url = %url, "Received recrawl request");
+        self.crawl_url(url, true, job_id).await
+    }
+
+    async fn delete_page(&self, request: Request<PageRequest>) -> Result<Response<()>, Status> {
+        let PageRequest { url } = request.into_inner();
+        let url = parse_url(&url)?;
+        match delete(&url, &self.state.index).await {
+            Ok(true) => {
+                tracing::info!(url = %url, "Deleted page");
+                Ok(Response::new(()))
+            }
+            Ok(false) => Err(Status::not_found(format!("{url} has not been crawled"))),
+            Err(e) => {
+                tracing::error!(url = %url, "Failed to delete page: {e:#}");
+                Err(Status::internal(e.to_string()))
+            }
+        }
+    }
+
+    async fn delete_prefix(
+        &self,
+        request: Request<PrefixRequest>,
+    ) -> Result<Response<DeletePrefixResponse>, Status> {
+        let PrefixRequest { prefix } = request.into_inner();
+        // A prefix short of a host would match every page, and one within a host the pages
+        // of other hosts, so only parsed URLs with a host are taken.
+        let url = parse_url(&prefix)?;
+        if url.host().is_none() {
+            return Err(Status::invalid_argument(format!("{prefix} has no host")));
+        }
+        let prefix = url.to_string();
+        match delete_prefix(&url, &self.state.index).await {
+            Ok(deleted) => {
+                tracing::info!(prefix, deleted, "Deleted pages");
+                Ok(Response::new(DeletePrefixResponse { deleted }))
+            }
+            Err(e) => {
+                tracing::error!(prefix, "Failed to delete pages: {e:#}");
+                Err(Status::internal(e.to_string()))
+            }
+        }
+    }
+
+    async fn inspect(
+        &self,
+        request: Request<CrawlRequest>,
+    ) -> Result<Response<InspectResponse>, Status> {
+        let CrawlRequest { url, .. } = request.into_inner();
+        let url = parse_url(&url)?;
+        tracing::debug!(url = %url, "Received inspect request");
+
+        let robots_allowed = is_robots_allowed(&url, &self.state).await.map_err(|e| {
+            tracing::error!(url = %url, "Failed to check robots.txt: {e:#}");
+            Status::internal(e.to_string())
+        })?;
+        let mut response = InspectResponse {
+            robots_allowed,
+            ..Default::default()
+        };
+        if !robots_allowed {
+            return Ok(Response::new(response));
+        }
+
+        let content = get_content(&url, &self.state).await.map_err(|e| {
+            tracing::error!(url = %url, "Failed to fetch content: {e:#}");
+            Status::unavailable(format!("{e:#}"))
+        })?;
+        if let Some(content) = content {
+            let Extracted {
+                title, body, links, ..
+            } = extract(&url, &content);
+            response.content_length = content.len() as u64;
+            response.title = title;
+            response.body = body;
+            response.links = links.into_iter().map(String::from).collect();
+        }
+        Ok(Response::new(response))
+    }
+
+    async fn create_job(
+        &self,
+        request: Request<CreateJobRequest>,
+    ) -> Result<Response<proto::Job>, Status> {
+        let CreateJobRequest {
+            name,
+            seeds,
+            scope,
+            budget,
+        } = request.into_inner();
+        let scope = JobScope::try_from(scope)
+            .map_err(|_| Status::invalid_argument(format!("Invalid scope {scope}")))?;
+        let seeds = seeds
+            .iter()
+            .map(|seed| {
+                let url = parse_url(seed)?;
+                match url.domain() {
+                    Some(_) => Ok(url),
+                    None => Err(Status::invalid_argument(format!(
+                        "Missing domain for {url}"
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if seeds.is_empty() {
+            return Err(Status::invalid_argument("At least one seed is required"));
+        }
+
+        let budget = i64::try_from(budget)
+            .map_err(|_| Status::invalid_argument(format!("Invalid budget {budget}")))?;
+        match job::create(name, seeds, scope.into(), budget, &self.state).await {
+            Ok(job) => Ok(Response::new(job.into())),
+            Err(e) => {
+                tracing::error!("Failed to create job: {e:#}");
+                Err(Status::internal(e.to_string()))
+            }
+        }
+    }
+
+    async fn get_job(&self, request: Request<JobRequest>) -> Result<Response<proto::Job>, Status> {
+        let uuid = parse_job_id(&request.into_inner().id)?;
+        job_response(job::get(uuid, &self.state).await)
+    }
+
+    type WatchJobStream = Pin<Box<dyn Stream<Item = Result<proto::Job, Status>> + Send + 'static>>;
+
+    async fn watch_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<Self::WatchJobStream>, Status> {
+        let uuid = parse_job_id(&request.into_inner().id)?;
+        let initial = job_response(job::get(uuid, &self.state).await)?.into_inner();
+
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            let mut updated = initial.updated;
+            let mut finished = initial.state != i32::from(JobState::Running)
+                && initial.state != i32::from(JobState::Paused);
+            if tx.send(Ok(initial)).await.is_err() {
+                return;
+            }
+            let mut interval = tokio::time::interval(WATCH_INTERVAL);
+            while !finished {
+                interval.tick().await;
+                let job = match job::get(uuid, &state).await {
+                    Ok(Some(job)) => proto::Job::from(job),
+                    Ok(None) => {
+                        let _ = tx.send(Err(Status::not_found("Job not found"))).await;
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::error!(uuid = ?uuid, "Failed to watch job: {e:#}");
+                        let _ = tx.send(Err(Status::internal(e.to_string()))).await;
+                        return;
+                    }
+                };
+                if job.updated == updated {
+                    continue;
+                }
+                updated = job.updated;
+                finished = job.state == i32::from(JobState::Cancelled)
+                    || job.state == i32::from(JobState::Completed);
+                if tx.send(Ok(job)).await.is_err() {
+                    tracing::debug!(uuid = ?uuid, "Watcher disconnected");
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::WatchJobStream
+        ))
+    }
+
+    async fn pause_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<proto::Job>, Status> {
+        let uuid = parse_job_id(&request.into_inner().id)?;
+        tracing::info!(uuid = ?uuid, "Pausing job");
+        job_response(
+            job::transition(
+                uuid,
+                &[database::JobState::Running],
+                database::JobState::Paused,
+                &self.state,
+            )
+            .await,
+        )
+    }
+
+    async fn resume_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<proto::Job>, Status> {
+        let uuid = parse_job_id(&request.into_inner().id)?;
+        tracing::info!(uuid = ?uuid, "Resuming job");
+        let job = job::transition(
+            uuid,
+            &[database::JobState::Paused],
+            database::JobState::Running,
+            &self.state,
+        )
+        .await;
+        self.release_deferred(uuid, &job).await;
+        job_response(job)
+    }
+
+    async fn cancel_job(
+        &self,
+        request: Request<JobRequest>,
+    ) -> Result<Response<proto::Job>, Status> {
+        let uuid = parse_job_id(&request.into_inner().id)?;
+        tracing::info!(uuid = ?uuid, "Cancelling job");
+        let job = job::transition(
+            uuid,
+            &[database::JobState::Running, database::JobState::Paused],
+            database::JobState::Cancelled,
+            &self.state,
+        )
+        .await;
+        self.release_deferred(uuid, &job).await;
+        job_response(job)
+    }
+}
+
 async fn serve() {
     let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
     health_reporter
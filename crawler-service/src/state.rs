@@ -1,21 +1,62 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use config::{Config, Environment};
 use qdrant_client::Qdrant;
 use serde::Deserialize;
-use utils::database::{init_mongo, init_qdrant};
-
-use crate::proto::embed_client::EmbedClient;
+use utils::{
+    database::{init_mongo, init_qdrant},
+    embed::{
+        self, Embedder, EmbedderConfig, EmbedderKind, SparseEmbedder, SparseEmbedderConfig,
+        SparseEmbedderKind,
+    },
+};
 
 pub const APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
+/// Clients required to persist a page and its embeddings.
+#[derive(Clone)]
+pub struct IndexState {
+    pub qdrant_client: Arc<Qdrant>,
+    pub mongo_client: mongodm::mongo::Client,
+    pub embedder: Arc<dyn Embedder>,
+    pub sparse_embedder: Arc<dyn SparseEmbedder>,
+    /// Where changes of the index are counted for the backend cache, if set.
+    pub redis_client: Option<redis::Client>,
+}
+
+impl IndexState {
+    pub async fn new(
+        mongo_uri: &str,
+        qdrant_uri: &str,
+        embedder: EmbedderConfig,
+        sparse_embedder: SparseEmbedderConfig,
+        redis_client: Option<redis::Client>,
+    ) -> Self {
+        let vector_dim = embedder.dimension;
+        let embedder = embed::connect(embedder)
+            .await
+            .expect("Failed to initialize embedder");
+        let sparse_embedder = embed::connect_sparse(sparse_embedder)
+            .await
+            .expect("Failed to initialize sparse embedder");
+
+        Self {
+            qdrant_client: Arc::new(
+                init_qdrant(vector_dim, sparse_embedder.idf(), qdrant_uri).await,
+            ),
+            mongo_client: init_mongo(mongo_uri).await.unwrap(),
+            embedder,
+            sparse_embedder,
+            redis_client,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub redis_client: redis::Client,
     pub reqwest_client: reqwest::Client,
-    pub qdrant_client: Arc<Qdrant>,
-    pub mongo_client: mongodm::mongo::Client,
-    pub tei_client: EmbedClient<tonic::transport::Channel>,
+    pub index: IndexState,
     pub logstash_uri: String,
     pub amqp_channel: lapin::Channel,
 }
@@ -25,8 +66,17 @@ struct AppConfig {
     pub redis_uri: String,
     pub qdrant_uri_write: String,
     pub mongo_uri_write: String,
-    pub tei_uri: String,
+    #[serde(default)]
+    pub embedder: EmbedderKind,
+    pub tei_uri: Option<String>,
+    /// Batches concurrent TEI gRPC requests arriving within this many milliseconds.
+    pub embedder_batch_window_ms: Option<u64>,
+    pub embedder_model_dir: Option<PathBuf>,
     pub vector_dim: u64,
+    #[serde(default)]
+    pub sparse_embedder: SparseEmbedderKind,
+    /// Address of TEI serving a SPLADE model, required by the `tei` sparse embedder.
+    pub tei_sparse_uri: Option<String>,
     pub logstash_uri: String,
     pub amqp_uri: String,
 }
@@ -53,8 +103,6 @@ impl AppState {
             .build()
             .unwrap();
 
-        let tei_client = EmbedClient::connect(app_config.tei_uri).await.unwrap();
-
         let options = lapin::ConnectionProperties::default();
         // .with_executor(tokio_executor_trait::Tokio::current())
         // .with_reactor(tokio_reactor_trait::Tokio);
@@ -64,13 +112,27 @@ impl AppState {
         let amqp_channel = connection.create_channel().await.unwrap();
 
         Self {
-            redis_client,
+            redis_client: redis_client.clone(),
             reqwest_client,
-            qdrant_client: Arc::new(
-                init_qdrant(app_config.vector_dim, &app_config.qdrant_uri_write).await,
-            ),
-            mongo_client: init_mongo(&app_config.mongo_uri_write).await.unwrap(),
-            tei_client,
+            index: IndexState::new(
+                &app_config.mongo_uri_write,
+                &app_config.qdrant_uri_write,
+                EmbedderConfig {
+                    kind: app_config.embedder,
+                    tei_uri: app_config.tei_uri,
+                    batch_window: app_config
+                        .embedder_batch_window_ms
+                        .map(Duration::from_millis),
+                    model_dir: app_config.embedder_model_dir,
+                    dimension: app_config.vector_dim,
+                },
+                SparseEmbedderConfig {
+                    kind: app_config.sparse_embedder,
+                    tei_uri: app_config.tei_sparse_uri,
+                },
+                Some(redis_client),
+            )
+            .await,
             logstash_uri: app_config.logstash_uri,
             amqp_channel,
         }
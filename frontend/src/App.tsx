@@ -1,23 +1,38 @@
 import { useState } from 'react';
-import { Input } from 'antd';
+import { Alert, Input, List } from 'antd';
+import type { components, operations } from './api';
+
+type SearchQuery = NonNullable<operations['search_get']['parameters']['query']>;
+type SearchResponse = components['schemas']['SearchResponse'];
+type ApiError = components['schemas']['ApiError'];
 
 const App = () => {
   const [loading, setLoading] = useState(false);
-  const [results, setResults] = useState([]);
+  const [response, setResponse] = useState<SearchResponse>();
+  const [error, setError] = useState<ApiError>();
   const { Search } = Input;
 
   const onSearch = async (value: string) => {
     setLoading(true);
-    const response = await fetch('http://localhost:8000/api/v1/search', {
-      method: 'GET',
-      headers: {
-        'Content-Type': 'application/json',
-        'Accept': 'application/json',
-      },
-      body: JSON.stringify({ query: value })
-    });
-    const data = await response.json();
-    setResults(data);
+    const query: SearchQuery = { q: value };
+    const params = new URLSearchParams({ q: query.q ?? '' });
+    try {
+      const response = await fetch(`http://localhost:8000/api/v1/search?${params}`, {
+        headers: {
+          'Accept': 'application/json',
+        },
+      });
+      const data = await response.json();
+      if (response.ok) {
+        setResponse(data as SearchResponse);
+        setError(undefined);
+      } else {
+        setResponse(undefined);
+        setError(data as ApiError);
+      }
+    } finally {
+      setLoading(false);
+    }
   }
 
   return (<div>
@@ -28,7 +43,22 @@ const App = () => {
       loading={loading}
       onSearch={onSearch}
     />
-    {results}
+    {error && <Alert
+      type="error"
+      message={error.message}
+      description={error.fields?.map((field) => `${field.field}: ${field.message}`).join('; ')}
+    />}
+    {response && <List
+      dataSource={response.matches}
+      renderItem={(result) => (
+        <List.Item key={result.id}>
+          <List.Item.Meta
+            title={<a href={result.url}>{result.title || result.url}</a>}
+            description={result.snippet?.text ?? result.url}
+          />
+        </List.Item>
+      )}
+    />}
   </div>);
 }
 
@@ -0,0 +1,1167 @@
+/**
+ * This file was auto-generated by openapi-typescript.
+ * Do not make direct changes to the file.
+ */
+
+export interface paths {
+    "/api/v1/search": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /** Searches pages, with the common parameters in the query string. */
+        get: operations["search_get"];
+        put?: never;
+        /** Searches pages, with every parameter in a JSON body. */
+        post: operations["search_post"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/suggest": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /** Completions of a query prefix, most popular first. */
+        get: operations["suggest"];
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/click": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /** Records a result opened from a search. */
+        post: operations["click"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/pages/{uuid}": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                /** @description UUID of the page */
+                uuid: string;
+            };
+            cookie?: never;
+        };
+        /** Crawl record of a page. */
+        get: operations["page"];
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/pages/{uuid}/similar": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                /** @description UUID of the page */
+                uuid: string;
+            };
+            cookie?: never;
+        };
+        /** Pages similar to a page, with the parameters in the query string. */
+        get: operations["similar_get"];
+        put?: never;
+        /** Pages similar to a page, with the parameters in a JSON body. */
+        post: operations["similar_post"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/admin/seeds": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /** Crawls seed URLs, each indexed before the response. */
+        post: operations["seed"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/admin/domains": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /** Domain queues with their depths. */
+        get: operations["domains"];
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/admin/domains/{domain}/pause": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                domain: string;
+            };
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /** Stops the selector from crawling the queue of a domain. */
+        post: operations["pause"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/admin/domains/{domain}/resume": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                domain: string;
+            };
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /** Lets the selector crawl the queue of a paused domain again. */
+        post: operations["resume"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/admin/recrawl": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /** Crawls a URL again even if it was crawled recently. */
+        post: operations["recrawl"];
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/api/v1/admin/pages": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        post?: never;
+        /** Deletes a page, or every page under a URL prefix, from the index. */
+        delete: operations["delete"];
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+}
+export type webhooks = Record<string, never>;
+export interface components {
+    schemas: {
+        ApiError: {
+            message: string;
+            error: components["schemas"]["ErrorType"];
+            /** @description Invalid fields of the request. */
+            fields?: components["schemas"]["FieldError"][];
+        };
+        /** @description Result opened from a search. */
+        ClickRequest: {
+            /** @description `search_id` of the search response. */
+            search_id: string;
+            /** @description `id` of the result. */
+            page_id: string;
+            /** @description Position of the result in the search, starting at 0. */
+            position: number;
+        };
+        /** @description Score of a result in a ranking, with its position starting at 1. */
+        Component: {
+            score: number;
+            rank: number;
+        };
+        /** @description Inclusive range of timestamps, open-ended on the missing side. */
+        DateRange: {
+            from?: string | null;
+            to?: string | null;
+        };
+        DeleteResponse: {
+            /** @description Number of pages deleted. */
+            deleted: number;
+        };
+        /** @description Crawl queue of a domain. */
+        DomainQueue: {
+            domain: string;
+            /** @description Number of URLs waiting in the queue. */
+            depth: number;
+            /** @description Whether the selector skips the queue. */
+            paused: boolean;
+        };
+        DomainsResponse: {
+            /** @description Queues, deepest first, and paused domains without one. */
+            domains: components["schemas"]["DomainQueue"][];
+        };
+        ErrorType: "BadRequest" | "Unauthorized" | "Forbidden" | "NotFound" | "TooManyRequests" | "InternalServerError";
+        /** @description Rankings a result went through, absent from those it was not part of. */
+        Explanation: {
+            /** @description Cosine similarity of the embeddings. */
+            dense?: null | components["schemas"]["Component"];
+            /** @description Sparse term vector score. */
+            sparse?: null | components["schemas"]["Component"];
+            /** @description Weighted reciprocal rank fusion of the dense and sparse rankings, once phrases
+     *     and the domain cap are applied. */
+            fused?: null | components["schemas"]["Component"];
+            /** @description Cross-encoder score. */
+            rerank?: null | components["schemas"]["Component"];
+            /** @description Learned ranking model score. */
+            ltr?: null | components["schemas"]["Component"];
+        };
+        FieldError: {
+            /** @description Path of the field, e.g. `filter.first.from`. */
+            field: string;
+            message: string;
+        };
+        /** @description Range of a snippet matching a query term, in characters. */
+        Highlight: {
+            start: number;
+            end: number;
+        };
+        MatchResult: {
+            /** @description UUID of the page. */
+            id: string;
+            title: string;
+            url: string;
+            /** @description Score of the last ranking the result went through. */
+            score: number;
+            snippet?: null | components["schemas"]["Snippet"];
+            explanation?: null | components["schemas"]["Explanation"];
+        };
+        /** @description Metadata extracted from the last crawled version of a page. */
+        Metadata: {
+            title?: string | null;
+            lang?: string | null;
+            mime?: string | null;
+        };
+        /** @description Crawl metadata of a page. */
+        PageResponse: {
+            uuid: string;
+            url: string;
+            first: string;
+            last: string;
+            /** @description SHA-256 digest of the last crawled content. */
+            sha256: string;
+            status: components["schemas"]["Status"];
+            error?: string | null;
+            links: number;
+            metadata: components["schemas"]["Metadata"];
+            /** @description UUIDs of the crawl jobs that discovered the page. */
+            jobs: string[];
+        };
+        /** @description How a query was interpreted, the filters and ranking stages that applied. */
+        QueryExplanation: {
+            /** @description Free text left once operators are removed, which is embedded. */
+            text: string;
+            phrases: string[];
+            excluded_terms: string[];
+            excluded_phrases: string[];
+            /** @description Filter of the request combined with those of the query operators. */
+            filter: components["schemas"]["SearchFilter"];
+            dense_weight: number;
+            sparse_weight: number;
+            /** @description Number of top candidates reranked, absent without reranking. */
+            rerank_depth?: number | null;
+            max_per_domain?: number | null;
+            mmr_lambda?: number | null;
+            /** @description Whether the top candidates were reordered by the learned ranking model. */
+            ltr: boolean;
+        };
+        RecrawlRequest: {
+            url: string;
+        };
+        RejectedUrl: {
+            url: string;
+            reason: string;
+        };
+        /** @description Criteria every result must meet, lists match any of their values. */
+        SearchFilter: {
+            domains?: string[];
+            exclude_domains?: string[];
+            /** @description Primary language subtags, e.g. `en`. */
+            lang?: string[];
+            /** @description Content types, e.g. `text/html` or `application/pdf`. */
+            mime?: string[];
+            /** @description When the page was first crawled. */
+            first?: null | components["schemas"]["DateRange"];
+            /** @description When the page was last crawled. */
+            last?: null | components["schemas"]["DateRange"];
+        };
+        SearchRequest: {
+            query: string;
+            limit?: number | null;
+            offset?: number | null;
+            /** @description `next_cursor` of the previous page, instead of `offset`. */
+            cursor?: string | null;
+            /** @description Weight of the semantic ranking in the fusion, skipped if 0. */
+            dense_weight?: number | null;
+            /** @description Weight of the exact-term ranking in the fusion, skipped if 0. */
+            sparse_weight?: number | null;
+            /** @description Reorders the top candidates with the cross-encoder, if one is configured. */
+            rerank?: boolean | null;
+            /** @description Number of top candidates reranked. */
+            rerank_depth?: number | null;
+            filter?: components["schemas"]["SearchFilter"];
+            /** @description Maximum number of results from the same domain. */
+            max_per_domain?: number | null;
+            /** @description Reorders the results by maximal marginal relevance, trading relevance (1) for
+     *     diversity (0). */
+            mmr_lambda?: number | null;
+            /** @description Explains how the query was interpreted and every result ranked. */
+            explain?: boolean | null;
+            /** @description Reorders the top candidates with the learned ranking model, if one is loaded,
+     *     instead of the A/B assignment of the session. */
+            ltr?: boolean | null;
+        };
+        SearchResponse: {
+            matches: components["schemas"]["MatchResult"][];
+            /** @description Opaque cursor of the next page, absent on the last one. */
+            next_cursor?: string | null;
+            /** @description Approximate number of pages matching the query. */
+            total?: number | null;
+            explanation?: null | components["schemas"]["QueryExplanation"];
+            /** @description Id of the logged search, which clicks refer to. */
+            search_id?: string | null;
+        };
+        /** @description URLs to crawl, each indexed before the response. */
+        SeedRequest: {
+            urls: string[];
+        };
+        SeedResponse: {
+            /** @description Number of URLs crawled. */
+            crawled: number;
+            /** @description URLs the crawler failed to crawl. */
+            rejected: components["schemas"]["RejectedUrl"][];
+        };
+        SimilarRequest: {
+            limit?: number | null;
+            offset?: number | null;
+            filter?: components["schemas"]["SearchFilter"];
+            /** @description UUIDs of pages the results should not be like. */
+            negative?: string[];
+        };
+        Snippet: {
+            text: string;
+            highlights: components["schemas"]["Highlight"][];
+        };
+        /** @description Outcome of the last crawl of a page. */
+        Status: "indexed" | "empty" | "disallowed" | "failed";
+        SuggestResponse: {
+            suggestions: string[];
+        };
+    };
+    responses: never;
+    parameters: never;
+    requestBodies: never;
+    headers: never;
+    pathItems: never;
+}
+export type $defs = Record<string, never>;
+export interface operations {
+    search_get: {
+        parameters: {
+            query?: {
+                q?: string;
+                limit?: number | null;
+                offset?: number | null;
+                cursor?: string | null;
+                explain?: boolean | null;
+                domains?: string | null;
+                exclude_domains?: string | null;
+                lang?: string | null;
+                mime?: string | null;
+                first_from?: string | null;
+                first_to?: string | null;
+                last_from?: string | null;
+                last_to?: string | null;
+            };
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Page of results */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["SearchResponse"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Rate limit exceeded */
+            429: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Internal error */
+            500: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    search_post: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody: {
+            content: {
+                "application/json": components["schemas"]["SearchRequest"];
+            };
+        };
+        responses: {
+            /** @description Page of results */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["SearchResponse"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Rate limit exceeded */
+            429: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Internal error */
+            500: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    suggest: {
+        parameters: {
+            query?: {
+                /** @description Prefix typed so far. */
+                q?: string;
+                limit?: number | null;
+            };
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Suggestions */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["SuggestResponse"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Rate limit exceeded */
+            429: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Internal error */
+            500: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    click: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody: {
+            content: {
+                "application/json": components["schemas"]["ClickRequest"];
+            };
+        };
+        responses: {
+            /** @description Click recorded */
+            204: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content?: never;
+            };
+            /** @description Unknown search or analytics disabled */
+            404: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Rate limit exceeded */
+            429: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Internal error */
+            500: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    page: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                /** @description UUID of the page */
+                uuid: string;
+            };
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Page */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["PageResponse"];
+                };
+            };
+            /** @description Unknown page */
+            404: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Rate limit exceeded */
+            429: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Internal error */
+            500: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    similar_get: {
+        parameters: {
+            query?: {
+                limit?: number | null;
+                offset?: number | null;
+                /** @description Comma-separated UUIDs. */
+                negative?: string | null;
+                domains?: string | null;
+                exclude_domains?: string | null;
+                lang?: string | null;
+                mime?: string | null;
+                first_from?: string | null;
+                first_to?: string | null;
+                last_from?: string | null;
+                last_to?: string | null;
+            };
+            header?: never;
+            path: {
+                /** @description UUID of the page */
+                uuid: string;
+            };
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Page of results */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["SearchResponse"];
+                };
+            };
+            /** @description Unknown page */
+            404: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Rate limit exceeded */
+            429: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Internal error */
+            500: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    similar_post: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                /** @description UUID of the page */
+                uuid: string;
+            };
+            cookie?: never;
+        };
+        requestBody: {
+            content: {
+                "application/json": components["schemas"]["SimilarRequest"];
+            };
+        };
+        responses: {
+            /** @description Page of results */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["SearchResponse"];
+                };
+            };
+            /** @description Unknown page */
+            404: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Rate limit exceeded */
+            429: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Internal error */
+            500: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    seed: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody: {
+            content: {
+                "application/json": components["schemas"]["SeedRequest"];
+            };
+        };
+        responses: {
+            /** @description Seeds crawled */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["SeedResponse"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description API key is not an admin key */
+            403: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    domains: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Domain queues */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["DomainsResponse"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description API key is not an admin key */
+            403: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    pause: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                domain: string;
+            };
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Domain paused */
+            204: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content?: never;
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description API key is not an admin key */
+            403: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    resume: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path: {
+                domain: string;
+            };
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Domain resumed */
+            204: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content?: never;
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description API key is not an admin key */
+            403: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    recrawl: {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody: {
+            content: {
+                "application/json": components["schemas"]["RecrawlRequest"];
+            };
+        };
+        responses: {
+            /** @description URL crawled */
+            204: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content?: never;
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description API key is not an admin key */
+            403: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+    delete: {
+        parameters: {
+            query?: {
+                url?: string | null;
+                prefix?: string | null;
+            };
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        requestBody?: never;
+        responses: {
+            /** @description Pages deleted */
+            200: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["DeleteResponse"];
+                };
+            };
+            /** @description Invalid request */
+            400: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Missing or invalid API key */
+            401: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description API key is not an admin key */
+            403: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+            /** @description Unknown URL */
+            404: {
+                headers: {
+                    [name: string]: unknown;
+                };
+                content: {
+                    "application/json": components["schemas"]["ApiError"];
+                };
+            };
+        };
+    };
+}
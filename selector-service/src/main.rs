@@ -1,7 +1,8 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 use anyhow::Context;
 use config::{Config, Environment};
+use lapin::{types::AMQPValue, BasicProperties};
 use proto::CrawlRequest;
 use rabbitmq_management_client::api::queue::QueueApi;
 use rand::seq::IteratorRandom;
@@ -23,6 +24,24 @@ pub struct QueueInfo {
     pub messages: u32,
 }
 
+/// AMQP header carrying the id of the job that discovered a URL.
+const JOB_HEADER: &str = "job";
+
+fn job_id(properties: &BasicProperties) -> Option<String> {
+    properties
+        .headers()
+        .as_ref()?
+        .inner()
+        .iter()
+        .find(|(key, _)| key.as_str() == JOB_HEADER)
+        .and_then(|(_, value)| match value {
+            AMQPValue::LongString(value) => {
+                Some(String::from_utf8_lossy(value.as_bytes()).into_owned())
+            }
+            _ => None,
+        })
+}
+
 async fn step(state: &AppState) -> anyhow::Result<()> {
     let queues = state
         .management_client
@@ -38,6 +57,19 @@ async fn step(state: &AppState) -> anyhow::Result<()> {
             .map(|q| mc.delete_queue(String::from("/"), q.name));
         futures::future::join_all(tasks).await;
     });
+    let mut conn = state
+        .redis_client
+        .get_multiplexed_async_connection()
+        .await
+        .context("get_multiplexed_async_connection")?;
+    let paused: HashSet<String> = conn
+        .smembers(Key::PausedDomains)
+        .await
+        .context("smembers")?;
+    let full: Vec<_> = full
+        .into_iter()
+        .filter(|queue| !paused.contains(&queue.name))
+        .collect();
     if full.is_empty() {
         tracing::debug!("No full queues");
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
@@ -47,11 +79,6 @@ async fn step(state: &AppState) -> anyhow::Result<()> {
         .iter()
         .map(|queue| Key::Cooldown(&queue.name))
         .collect();
-    let mut conn = state
-        .redis_client
-        .get_multiplexed_async_connection()
-        .await
-        .context("get_multiplexed_async_connection")?;
     let results: Vec<Option<u8>> = conn.mget(&reset_keys).await.context("mget")?;
     let maybe_domain = {
         let mut rng = rand::thread_rng();
@@ -87,11 +114,12 @@ async fn step(state: &AppState) -> anyhow::Result<()> {
                     return Ok(());
                 }
             };
-            tracing::info!(domain = domain.name, url = url, "Got url");
+            let job_id = job_id(&msg.delivery.properties);
+            tracing::info!(domain = domain.name, url = url, job = ?job_id, "Got url");
             if let Err(e) = state
                 .crawler_client
                 .clone()
-                .crawl(CrawlRequest { url })
+                .crawl(CrawlRequest { url, job_id })
                 .await
             {
                 tracing::error!(error = %e, "Failed to crawl");
@@ -0,0 +1,4 @@
+fn main() {
+    #[cfg(feature = "tei")]
+    tonic_build::compile_protos("../proto/tei.proto").unwrap();
+}
@@ -1,4 +1,4 @@
-use bson::Uuid;
+use bson::{Bson, Uuid};
 use chrono::{DateTime, Utc};
 use mongodm::f;
 use mongodm::mongo::{
@@ -7,13 +7,34 @@ use mongodm::mongo::{
 use mongodm::{sync_indexes, CollectionConfig, Index, IndexOption, Indexes, Model};
 use qdrant_client::qdrant::CreateCollectionBuilder;
 use qdrant_client::{
-    qdrant::{vectors_config::Config as QConfig, Distance, VectorParams, VectorsConfig},
+    qdrant::{
+        vectors_config::Config as QConfig, CreateFieldIndexCollectionBuilder, Distance, FieldType,
+        Modifier, SparseVectorParams, SparseVectorsConfigBuilder, VectorParams, VectorsConfig,
+    },
     Qdrant,
 };
 use serde::{Deserialize, Serialize};
 
 pub const DATABASE: &str = "crawler";
 pub const COLLNAME: &str = "pages";
+/// Name of the sparse term vector of the points in [`COLLNAME`], next to the unnamed
+/// dense vector.
+pub const SPARSE_VECTOR: &str = "sparse";
+
+/// Payload fields of the points in [`COLLNAME`] that searches can be filtered by.
+pub const PAYLOAD_INDEXES: [(&str, FieldType); 6] = [
+    ("domain", FieldType::Keyword),
+    ("lang", FieldType::Keyword),
+    ("mime", FieldType::Keyword),
+    ("first", FieldType::Datetime),
+    ("last", FieldType::Datetime),
+    ("passages", FieldType::Text),
+];
+pub const JOBS_COLLNAME: &str = "jobs";
+pub const QUERIES_COLLNAME: &str = "queries";
+pub const CLICKS_COLLNAME: &str = "clicks";
+/// Time after which query log entries and clicks expire.
+pub const ANALYTICS_TTL_SECS: i32 = 60 * 60 * 24 * 90;
 
 pub struct PagesCollConf;
 
@@ -25,12 +46,49 @@ impl CollectionConfig for PagesCollConf {
     fn indexes() -> Indexes {
         Indexes::new()
             .with(Index::new(f!(url in Page)).with_option(IndexOption::Unique))
+            .with(Index::new(f!(uuid in Page)).with_option(IndexOption::Unique))
             .with(Index::new(f!(first in Page)))
             .with(Index::new(f!(last in Page)))
             .with(Index::new(f!(sha256 in Page)))
     }
 }
 
+/// Outcome of the last crawl of a page.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Indexed,
+    Empty,
+    Disallowed,
+    Failed,
+}
+
+impl From<Status> for Bson {
+    fn from(value: Status) -> Self {
+        let value = match value {
+            Status::Indexed => "indexed",
+            Status::Empty => "empty",
+            Status::Disallowed => "disallowed",
+            Status::Failed => "failed",
+        };
+        Bson::String(value.to_owned())
+    }
+}
+
+/// Metadata extracted from the last crawled version of a page.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Page {
     pub url: String,
@@ -40,6 +98,21 @@ pub struct Page {
     pub last: DateTime<Utc>,
     pub sha256: String,
     pub uuid: Uuid,
+    #[serde(default)]
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Crawl jobs that discovered the page.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jobs: Vec<Uuid>,
+    /// Number of distinct outgoing links of the last crawled version.
+    #[serde(default)]
+    pub links: i64,
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// Distinct texts of the outgoing links of the last crawled version.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anchors: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -51,24 +124,245 @@ impl Model for UuidProjection {
     type CollConf = PagesCollConf;
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct FirstSeenProjection {
+    pub uuid: Uuid,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub first: DateTime<Utc>,
+}
+
+impl Model for FirstSeenProjection {
+    type CollConf = PagesCollConf;
+}
+
+/// Texts of a page users may search for.
+#[derive(Serialize, Deserialize)]
+pub struct TextsProjection {
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub anchors: Vec<String>,
+}
+
+impl Model for TextsProjection {
+    type CollConf = PagesCollConf;
+}
+
 impl Model for Page {
     type CollConf = PagesCollConf;
 }
 
+pub struct JobsCollConf;
+
+impl CollectionConfig for JobsCollConf {
+    fn collection_name() -> &'static str {
+        JOBS_COLLNAME
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new()
+            .with(Index::new(f!(uuid in Job)).with_option(IndexOption::Unique))
+            .with(Index::new(f!(state in Job)))
+    }
+}
+
+/// Which of the links discovered by a crawl job are followed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Links on the domains of the seeds.
+    Domain,
+    /// Links starting with one of the seeds.
+    Prefix,
+    /// Every link.
+    Any,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Paused,
+    Cancelled,
+    Completed,
+}
+
+impl From<JobState> for Bson {
+    fn from(value: JobState) -> Self {
+        let value = match value {
+            JobState::Running => "running",
+            JobState::Paused => "paused",
+            JobState::Cancelled => "cancelled",
+            JobState::Completed => "completed",
+        };
+        Bson::String(value.to_owned())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Job {
+    pub uuid: Uuid,
+    pub name: String,
+    pub seeds: Vec<String>,
+    pub scope: Scope,
+    /// Maximum number of pages to fetch, unlimited if `0`.
+    pub budget: i64,
+    pub state: JobState,
+    pub fetched: i64,
+    pub indexed: i64,
+    pub failed: i64,
+    /// URLs published on the domain queues and not fetched yet.
+    pub queued: i64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updated: DateTime<Utc>,
+}
+
+impl Model for Job {
+    type CollConf = JobsCollConf;
+}
+
+pub struct QueriesCollConf;
+
+impl CollectionConfig for QueriesCollConf {
+    fn collection_name() -> &'static str {
+        QUERIES_COLLNAME
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new()
+            .with(Index::new(f!(uuid in QueryLog)).with_option(IndexOption::Unique))
+            .with(
+                Index::new(f!(time in QueryLog))
+                    .with_option(IndexOption::ExpireAfterSeconds(ANALYTICS_TTL_SECS)),
+            )
+            .with(Index::new(f!(query in QueryLog)))
+    }
+}
+
+/// Search served by the backend.
+#[derive(Serialize, Deserialize)]
+pub struct QueryLog {
+    /// Id of the search, which clicks refer to.
+    pub uuid: Uuid,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub time: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Query as typed, operators included.
+    pub query: String,
+    pub filter: bson::Document,
+    /// Number of results before the page.
+    pub offset: i64,
+    /// UUIDs of the pages of the results, in order.
+    pub results: Vec<String>,
+    pub latency_ms: i64,
+    /// Whether the results were served from the cache.
+    pub cached: bool,
+    /// Ranking features of the results, in order, absent for cached results.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<Vec<f32>>,
+    /// Whether the results were ranked by the learned model, for A/B comparisons.
+    #[serde(default)]
+    pub ltr: bool,
+}
+
+impl Model for QueryLog {
+    type CollConf = QueriesCollConf;
+}
+
+pub struct ClicksCollConf;
+
+impl CollectionConfig for ClicksCollConf {
+    fn collection_name() -> &'static str {
+        CLICKS_COLLNAME
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new()
+            .with(Index::new(f!(search in Click)))
+            .with(
+                Index::new(f!(time in Click))
+                    .with_option(IndexOption::ExpireAfterSeconds(ANALYTICS_TTL_SECS)),
+            )
+            .with(Index::new(f!(query in Click)))
+    }
+}
+
+/// Result opened from a search.
+#[derive(Serialize, Deserialize)]
+pub struct Click {
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub time: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Id of the search the result was opened from.
+    pub search: Uuid,
+    /// Query of the search, for reports without a lookup.
+    pub query: String,
+    /// UUID of the page of the result.
+    pub page: String,
+    /// Position of the result in the search, starting at 0.
+    pub position: i64,
+}
+
+impl Model for Click {
+    type CollConf = ClicksCollConf;
+}
+
 pub async fn init_mongo(uri: &str) -> Result<MongoClient, MongoError> {
     tracing::debug!("Initializing MongoDB client");
     let client_options = ClientOptions::parse(uri).await?;
     let client = MongoClient::with_options(client_options)?;
     let db = client.database(DATABASE);
     sync_indexes::<PagesCollConf>(&db).await?;
+    sync_indexes::<JobsCollConf>(&db).await?;
     Ok(client)
 }
 
-pub async fn init_qdrant(size: u64, uri: &str) -> Qdrant {
+/// Panics unless the existing `pages` collection has the sparse vector, which upserts
+/// and searches all use.
+pub async fn ensure_sparse_vector(qdrant_client: &Qdrant) {
+    let info = qdrant_client
+        .collection_info(COLLNAME)
+        .await
+        .expect("Failed to read Qdrant collection");
+    let has_sparse = info
+        .result
+        .and_then(|info| info.config)
+        .and_then(|config| config.params)
+        .and_then(|params| params.sparse_vectors_config)
+        .is_some_and(|config| config.map.contains_key(SPARSE_VECTOR));
+    assert!(
+        has_sparse,
+        "Qdrant collection `{COLLNAME}` has no `{SPARSE_VECTOR}` vector, recreate it to enable hybrid search"
+    );
+}
+
+/// Connects to Qdrant, creates the `pages` collection if it does not exist and its
+/// [`PAYLOAD_INDEXES`] if they do not exist.
+///
+/// `sparse_idf` makes Qdrant weigh the sparse vectors by inverse document frequency,
+/// which BM25 term vectors rely on.
+pub async fn init_qdrant(size: u64, sparse_idf: bool, uri: &str) -> Qdrant {
     tracing::debug!("Initializing Qdrant client");
     let qdrant_client = Qdrant::from_url(uri).build().unwrap();
     if !qdrant_client.collection_exists(COLLNAME).await.unwrap() {
         tracing::debug!("Creating Qdrant collection");
+        let modifier = if sparse_idf {
+            Modifier::Idf
+        } else {
+            Modifier::None
+        };
+        let mut sparse_vectors_config = SparseVectorsConfigBuilder::default();
+        sparse_vectors_config.add_named_vector_params(
+            SPARSE_VECTOR,
+            SparseVectorParams {
+                modifier: Some(modifier.into()),
+                ..Default::default()
+            },
+        );
         qdrant_client
             .create_collection(
                 CreateCollectionBuilder::new(COLLNAME)
@@ -79,10 +373,22 @@ pub async fn init_qdrant(size: u64, uri: &str) -> Qdrant {
                             ..Default::default()
                         })),
                     })
+                    .sparse_vectors_config(sparse_vectors_config)
                     .build(),
             )
             .await
             .unwrap();
+    } else {
+        ensure_sparse_vector(&qdrant_client).await;
+    }
+    for (field, field_type) in PAYLOAD_INDEXES {
+        tracing::debug!(field = field, "Creating Qdrant payload index");
+        qdrant_client
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(COLLNAME, field, field_type).wait(true),
+            )
+            .await
+            .unwrap();
     }
     qdrant_client
 }
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::sync::{mpsc, oneshot};
+use tonic::transport::{Channel, Endpoint};
+
+use super::{
+    tei::proto::{
+        embed_client::EmbedClient, info_client::InfoClient, EmbedRequest, InfoRequest, InfoResponse,
+    },
+    Embedder,
+};
+
+/// Number of requests waiting for a batch before callers are slowed down.
+const QUEUE_SIZE: usize = 4096;
+
+struct Pending {
+    input: String,
+    tx: oneshot::Sender<anyhow::Result<Vec<f32>>>,
+}
+
+/// Batch limits advertised by the TEI `Info` RPC.
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    max_batch_size: usize,
+    max_batch_tokens: usize,
+    max_input_length: usize,
+}
+
+impl Limits {
+    /// Upper bound of the number of tokens `input` is truncated to.
+    ///
+    /// Tokens hardly ever span less than a character, so the character count is used
+    /// instead of tokenizing the input.
+    fn tokens(&self, input: &str) -> usize {
+        input.chars().count().clamp(1, self.max_input_length.max(1))
+    }
+}
+
+impl From<InfoResponse> for Limits {
+    fn from(info: InfoResponse) -> Self {
+        Self {
+            max_batch_size: info.max_client_batch_size.max(1) as usize,
+            max_batch_tokens: info.max_batch_tokens.max(1) as usize,
+            max_input_length: info.max_input_length as usize,
+        }
+    }
+}
+
+/// Embedder coalescing concurrent requests to TEI.
+///
+/// Requests arriving within `window` of the first one are sent together over
+/// `EmbedStream`, in batches no larger than the client batch size and batch token
+/// budget of the server, and the embeddings are handed back to each caller.
+pub struct BatchingEmbedder {
+    tx: mpsc::Sender<Pending>,
+    model_id: String,
+}
+
+impl BatchingEmbedder {
+    pub async fn connect(uri: String, window: Duration) -> anyhow::Result<Self> {
+        let channel = Endpoint::from_shared(uri)
+            .context("Invalid TEI URI")?
+            .connect()
+            .await
+            .context("Failed to connect to TEI")?;
+        let info = InfoClient::new(channel.clone())
+            .info(InfoRequest {})
+            .await
+            .context("TEI info")?
+            .into_inner();
+        let model_id = info.model_id.clone();
+        let limits = Limits::from(info);
+        tracing::debug!(limits = ?limits, window = ?window, "Initialized batching embedder");
+
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(collect(rx, EmbedClient::new(channel), limits, window));
+        Ok(Self { tx, model_id })
+    }
+}
+
+/// Groups pending requests into batches until every embedder handle is dropped.
+async fn collect(
+    mut rx: mpsc::Receiver<Pending>,
+    client: EmbedClient<Channel>,
+    limits: Limits,
+    window: Duration,
+) {
+    let mut overflow = None;
+    loop {
+        let first = match overflow.take() {
+            Some(pending) => pending,
+            None => match rx.recv().await {
+                Some(pending) => pending,
+                None => return,
+            },
+        };
+        let mut tokens = limits.tokens(&first.input);
+        let mut batch = vec![first];
+
+        let deadline = tokio::time::sleep(window);
+        tokio::pin!(deadline);
+        while batch.len() < limits.max_batch_size {
+            tokio::select! {
+                _ = &mut deadline => break,
+                pending = rx.recv() => {
+                    let Some(pending) = pending else {
+                        break;
+                    };
+                    let pending_tokens = limits.tokens(&pending.input);
+                    if tokens + pending_tokens > limits.max_batch_tokens {
+                        overflow = Some(pending);
+                        break;
+                    }
+                    tokens += pending_tokens;
+                    batch.push(pending);
+                }
+            }
+        }
+
+        tracing::debug!(
+            size = batch.len(),
+            tokens = tokens,
+            "Sending embedding batch"
+        );
+        tokio::spawn(send(client.clone(), batch));
+    }
+}
+
+async fn send(mut client: EmbedClient<Channel>, batch: Vec<Pending>) {
+    let (inputs, senders): (Vec<_>, Vec<_>) = batch
+        .into_iter()
+        .map(|Pending { input, tx }| (input, tx))
+        .unzip();
+    let requests = inputs.into_iter().map(|inputs| EmbedRequest {
+        inputs,
+        truncate: true,
+        normalize: true,
+        truncation_direction: 0,
+        prompt_name: None,
+    });
+
+    let result = async {
+        let mut stream = client
+            .embed_stream(tokio_stream::iter(requests))
+            .await
+            .context("TEI embed stream")?
+            .into_inner();
+        let mut embeddings = Vec::with_capacity(senders.len());
+        while let Some(response) = stream.message().await.context("TEI embed stream")? {
+            embeddings.push(response.embeddings);
+        }
+        anyhow::ensure!(
+            embeddings.len() == senders.len(),
+            "TEI returned {} embeddings for {} inputs",
+            embeddings.len(),
+            senders.len()
+        );
+        Ok(embeddings)
+    }
+    .await;
+
+    match result {
+        Ok(embeddings) => {
+            for (tx, embedding) in senders.into_iter().zip(embeddings) {
+                let _ = tx.send(Ok(embedding));
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to embed batch: {e:#}");
+            for tx in senders {
+                let _ = tx.send(Err(anyhow::anyhow!("{e:#}")));
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for BatchingEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    async fn embed(&self, input: String) -> anyhow::Result<Vec<f32>> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(Pending { input, tx })
+            .await
+            .map_err(|_| anyhow::anyhow!("Batching task stopped"))?;
+        rx.await.context("Batching task dropped the request")?
+    }
+
+    async fn embed_batch(&self, inputs: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        futures::future::try_join_all(inputs.into_iter().map(|input| self.embed(input))).await
+    }
+}
@@ -0,0 +1,96 @@
+use super::{fnv1a, normalize, words, Embedder};
+
+/// Deterministic embedder hashing words into a fixed number of buckets.
+///
+/// Texts sharing words end up close to each other, which is enough for tests and
+/// for running the pipeline without a model.
+pub struct HashEmbedder {
+    dimension: usize,
+    model_id: String,
+}
+
+impl HashEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            model_id: format!("hash:{dimension}"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for HashEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    async fn embed(&self, input: String) -> anyhow::Result<Vec<f32>> {
+        let mut vector = vec![0.0; self.dimension];
+        if self.dimension == 0 {
+            return Ok(vector);
+        }
+        for word in words(&input) {
+            let hash = fnv1a(word.as_bytes());
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimension as u64) as usize] += sign;
+        }
+        normalize(&mut vector);
+        Ok(vector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| a * b).sum()
+    }
+
+    async fn embed(input: &str) -> Vec<f32> {
+        HashEmbedder::new(64)
+            .embed(input.to_string())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn deterministic() {
+        assert_eq!(embed("async runtime").await, embed("async runtime").await);
+        assert_eq!(HashEmbedder::new(64).model_id(), "hash:64");
+    }
+
+    #[tokio::test]
+    async fn words_are_normalized() {
+        assert_eq!(embed("Async, RUNTIME!").await, embed("async runtime").await);
+        assert_eq!(embed("runtime async").await, embed("async runtime").await);
+    }
+
+    #[tokio::test]
+    async fn unit_length() {
+        for input in ["rust", "rust rust rust", "rust async runtime"] {
+            let vector = embed(input).await;
+            assert_eq!(vector.len(), 64);
+            assert!((dot(&vector, &vector) - 1.0).abs() < 1e-6, "{input}");
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_words_are_close() {
+        let query = embed("rust async runtime").await;
+        let related = dot(&query, &embed("async rust").await);
+        let unrelated = dot(&query, &embed("cooking pasta recipes").await);
+        assert!(related > unrelated, "{related} <= {unrelated}");
+    }
+
+    #[tokio::test]
+    async fn without_words() {
+        assert_eq!(embed("").await, vec![0.0; 64]);
+        assert_eq!(embed(" !? ").await, vec![0.0; 64]);
+        let vector = HashEmbedder::new(0)
+            .embed("rust".to_string())
+            .await
+            .unwrap();
+        assert!(vector.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::Context;
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use tokenizers::{Tokenizer, TruncationParams};
+
+use super::{normalize, Embedder};
+
+struct Model {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl Model {
+    /// Mean-pools the last hidden state over the tokens of `input`.
+    fn embed(&self, input: &str) -> anyhow::Result<Vec<f32>> {
+        let encoding = self
+            .tokenizer
+            .encode(input, true)
+            .map_err(anyhow::Error::msg)
+            .context("Tokenization")?;
+        let ids = Tensor::new(encoding.get_ids(), &self.device)?.unsqueeze(0)?;
+        let type_ids = ids.zeros_like()?;
+        let mask = Tensor::new(encoding.get_attention_mask(), &self.device)?.unsqueeze(0)?;
+        let hidden = self.model.forward(&ids, &type_ids, Some(&mask))?;
+        let mask = mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+        let sum = hidden.broadcast_mul(&mask)?.sum(1)?;
+        let count = mask.sum(1)?;
+        let mut embedding: Vec<f32> = sum.broadcast_div(&count)?.squeeze(0)?.to_vec1()?;
+        normalize(&mut embedding);
+        Ok(embedding)
+    }
+}
+
+/// In-process BERT embedder running on the CPU.
+pub struct LocalEmbedder {
+    model: Arc<Model>,
+    model_id: String,
+}
+
+impl LocalEmbedder {
+    /// Loads `config.json`, `tokenizer.json` and `model.safetensors` from `dir`.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let device = Device::Cpu;
+        let config = std::fs::read_to_string(dir.join("config.json"))
+            .context("Failed to read config.json")?;
+        let config: Config = serde_json::from_str(&config).context("Invalid config.json")?;
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(anyhow::Error::msg)
+            .context("Failed to load tokenizer.json")?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(anyhow::Error::msg)?;
+        // SAFETY: the weights are not modified while they are mapped.
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DTYPE, &device)
+                .context("Failed to load model.safetensors")?
+        };
+        let model = BertModel::load(vb, &config).context("Failed to load model")?;
+        tracing::info!(dir = %dir.display(), "Loaded local embedding model");
+        Ok(Self {
+            model: Arc::new(Model {
+                model,
+                tokenizer,
+                device,
+            }),
+            model_id: format!("local:{}", dir.display()),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for LocalEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    async fn embed(&self, input: String) -> anyhow::Result<Vec<f32>> {
+        let model = self.model.clone();
+        tokio::task::spawn_blocking(move || model.embed(&input))
+            .await
+            .context("Embedding task")?
+    }
+}
@@ -0,0 +1,177 @@
+use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+
+use serde::Deserialize;
+
+#[cfg(feature = "tei")]
+mod batch;
+mod hash;
+#[cfg(feature = "local")]
+mod local;
+mod sparse;
+#[cfg(feature = "tei")]
+pub mod tei;
+
+#[cfg(feature = "tei")]
+pub use batch::BatchingEmbedder;
+pub use hash::HashEmbedder;
+#[cfg(feature = "local")]
+pub use local::LocalEmbedder;
+pub use sparse::{Bm25Embedder, SparseEmbedder, SparseVector};
+#[cfg(feature = "tei")]
+pub use tei::{TeiGrpcEmbedder, TeiHttpEmbedder, TeiSparseEmbedder};
+
+/// Produces normalized dense embeddings of texts.
+#[async_trait::async_trait]
+pub trait Embedder: Send + Sync {
+    /// Identifier of the model, embeddings of different models are not comparable.
+    fn model_id(&self) -> &str;
+
+    async fn embed(&self, input: String) -> anyhow::Result<Vec<f32>>;
+
+    /// Embeds every input, preserving their order.
+    async fn embed_batch(&self, inputs: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            embeddings.push(self.embed(input).await?);
+        }
+        Ok(embeddings)
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbedderKind {
+    #[default]
+    TeiGrpc,
+    TeiHttp,
+    Local,
+    Hash,
+}
+
+impl FromStr for EmbedderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tei_grpc" => Ok(EmbedderKind::TeiGrpc),
+            "tei_http" => Ok(EmbedderKind::TeiHttp),
+            "local" => Ok(EmbedderKind::Local),
+            "hash" => Ok(EmbedderKind::Hash),
+            other => anyhow::bail!("Unknown embedder `{other}`"),
+        }
+    }
+}
+
+pub struct EmbedderConfig {
+    pub kind: EmbedderKind,
+    /// Address of TEI, required by the `tei_grpc` and `tei_http` embedders.
+    pub tei_uri: Option<String>,
+    /// Window within which concurrent requests to the `tei_grpc` embedder are batched.
+    pub batch_window: Option<Duration>,
+    /// Directory holding `config.json`, `tokenizer.json` and `model.safetensors`,
+    /// required by the `local` embedder.
+    pub model_dir: Option<PathBuf>,
+    pub dimension: u64,
+}
+
+pub async fn connect(config: EmbedderConfig) -> anyhow::Result<Arc<dyn Embedder>> {
+    tracing::debug!(kind = ?config.kind, "Initializing embedder");
+    match config.kind {
+        #[cfg(feature = "tei")]
+        EmbedderKind::TeiGrpc => {
+            let uri = config
+                .tei_uri
+                .ok_or_else(|| anyhow::anyhow!("`tei_uri` is required by the TEI embedder"))?;
+            match config.batch_window {
+                Some(window) => Ok(Arc::new(BatchingEmbedder::connect(uri, window).await?)),
+                None => Ok(Arc::new(TeiGrpcEmbedder::connect(uri).await?)),
+            }
+        }
+        #[cfg(feature = "tei")]
+        EmbedderKind::TeiHttp => {
+            let uri = config
+                .tei_uri
+                .ok_or_else(|| anyhow::anyhow!("`tei_uri` is required by the TEI embedder"))?;
+            Ok(Arc::new(TeiHttpEmbedder::connect(uri).await?))
+        }
+        #[cfg(feature = "local")]
+        EmbedderKind::Local => {
+            let model_dir = config.model_dir.ok_or_else(|| {
+                anyhow::anyhow!("`embedder_model_dir` is required by the local embedder")
+            })?;
+            Ok(Arc::new(LocalEmbedder::load(&model_dir)?))
+        }
+        EmbedderKind::Hash => Ok(Arc::new(HashEmbedder::new(config.dimension as usize))),
+        #[allow(unreachable_patterns)]
+        kind => anyhow::bail!("Embedder `{kind:?}` is not enabled in this build"),
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SparseEmbedderKind {
+    #[default]
+    Bm25,
+    /// SPLADE served by a Text Embeddings Inference instance.
+    Tei,
+}
+
+impl FromStr for SparseEmbedderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bm25" => Ok(SparseEmbedderKind::Bm25),
+            "tei" => Ok(SparseEmbedderKind::Tei),
+            other => anyhow::bail!("Unknown sparse embedder `{other}`"),
+        }
+    }
+}
+
+pub struct SparseEmbedderConfig {
+    pub kind: SparseEmbedderKind,
+    /// Address of the TEI instance serving a SPLADE model, required by the `tei` sparse
+    /// embedder.
+    pub tei_uri: Option<String>,
+}
+
+pub async fn connect_sparse(
+    config: SparseEmbedderConfig,
+) -> anyhow::Result<Arc<dyn SparseEmbedder>> {
+    tracing::debug!(kind = ?config.kind, "Initializing sparse embedder");
+    match config.kind {
+        SparseEmbedderKind::Bm25 => Ok(Arc::new(Bm25Embedder::default())),
+        #[cfg(feature = "tei")]
+        SparseEmbedderKind::Tei => {
+            let uri = config.tei_uri.ok_or_else(|| {
+                anyhow::anyhow!("`tei_sparse_uri` is required by the TEI sparse embedder")
+            })?;
+            Ok(Arc::new(TeiSparseEmbedder::connect(uri).await?))
+        }
+        #[allow(unreachable_patterns)]
+        kind => anyhow::bail!("Sparse embedder `{kind:?}` is not enabled in this build"),
+    }
+}
+
+/// 64-bit FNV-1a, stable across platforms and Rust versions.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Lowercased alphanumeric words of `input`.
+pub(crate) fn words(input: &str) -> impl Iterator<Item = String> + '_ {
+    input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Scales `vector` to unit length.
+pub(crate) fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
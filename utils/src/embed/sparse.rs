@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use super::{fnv1a, words};
+
+/// Sparse term vector, with indices in ascending order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SparseVector {
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+impl SparseVector {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    fn from_weights(weights: HashMap<u32, f32>) -> Self {
+        let mut weights: Vec<_> = weights.into_iter().collect();
+        weights.sort_unstable_by_key(|(index, _)| *index);
+        let (indices, values) = weights.into_iter().unzip();
+        Self { indices, values }
+    }
+}
+
+/// Produces sparse term vectors of texts, for exact-term matching.
+#[async_trait::async_trait]
+pub trait SparseEmbedder: Send + Sync {
+    async fn embed_document(&self, input: String) -> anyhow::Result<SparseVector>;
+
+    /// Embeds a search query, which may be weighted differently from documents.
+    async fn embed_query(&self, input: String) -> anyhow::Result<SparseVector> {
+        self.embed_document(input).await
+    }
+
+    /// Whether the collection has to apply inverse document frequency to the vectors.
+    fn idf(&self) -> bool {
+        false
+    }
+}
+
+/// Term saturation of BM25.
+const K1: f32 = 1.2;
+/// Length normalization of BM25.
+const B: f32 = 0.75;
+
+/// BM25 term frequencies of hashed words.
+///
+/// Only the term frequency part of BM25 is computed here, the inverse document
+/// frequency is applied by Qdrant at query time.
+pub struct Bm25Embedder {
+    /// Expected number of words of a document.
+    average_length: f32,
+}
+
+impl Bm25Embedder {
+    pub fn new(average_length: f32) -> Self {
+        Self {
+            average_length: average_length.max(1.0),
+        }
+    }
+}
+
+impl Default for Bm25Embedder {
+    fn default() -> Self {
+        Self::new(256.0)
+    }
+}
+
+fn term(word: &str) -> u32 {
+    fnv1a(word.as_bytes()) as u32
+}
+
+#[async_trait::async_trait]
+impl SparseEmbedder for Bm25Embedder {
+    async fn embed_document(&self, input: String) -> anyhow::Result<SparseVector> {
+        let mut frequencies = HashMap::new();
+        let mut length = 0;
+        for word in words(&input) {
+            *frequencies.entry(term(&word)).or_insert(0.0) += 1.0;
+            length += 1;
+        }
+        let norm = K1 * (1.0 - B + B * length as f32 / self.average_length);
+        frequencies
+            .values_mut()
+            .for_each(|tf| *tf = *tf * (K1 + 1.0) / (*tf + norm));
+        Ok(SparseVector::from_weights(frequencies))
+    }
+
+    async fn embed_query(&self, input: String) -> anyhow::Result<SparseVector> {
+        let weights = words(&input).map(|word| (term(&word), 1.0)).collect();
+        Ok(SparseVector::from_weights(weights))
+    }
+
+    fn idf(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn document(embedder: &Bm25Embedder, input: &str) -> SparseVector {
+        embedder.embed_document(input.to_string()).await.unwrap()
+    }
+
+    async fn query(input: &str) -> SparseVector {
+        Bm25Embedder::default()
+            .embed_query(input.to_string())
+            .await
+            .unwrap()
+    }
+
+    fn weight(vector: &SparseVector, word: &str) -> f32 {
+        let position = vector.indices.iter().position(|&index| index == term(word));
+        vector.values[position.unwrap()]
+    }
+
+    #[tokio::test]
+    async fn deterministic() {
+        let embedder = Bm25Embedder::default();
+        let input = "the async runtime runs async tasks";
+        assert_eq!(
+            document(&embedder, input).await,
+            document(&embedder, input).await
+        );
+        assert_eq!(query(input).await, query(input).await);
+    }
+
+    #[tokio::test]
+    async fn words_are_normalized() {
+        let embedder = Bm25Embedder::default();
+        assert_eq!(
+            document(&embedder, "Async, RUNTIME! async").await,
+            document(&embedder, "async runtime async").await
+        );
+        assert_eq!(query("Async RUNTIME").await, query("runtime, async").await);
+    }
+
+    #[tokio::test]
+    async fn indices_are_sorted_and_unique() {
+        let vector = document(&Bm25Embedder::default(), "b a c a b d e f").await;
+        assert_eq!(vector.indices.len(), 6);
+        assert_eq!(vector.values.len(), 6);
+        assert!(vector.indices.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[tokio::test]
+    async fn term_frequency_saturates() {
+        // At the average length, a word seen once weighs exactly 1.
+        let embedder = Bm25Embedder::new(4.0);
+        let vector = document(&embedder, "rust is a language").await;
+        assert!((weight(&vector, "rust") - 1.0).abs() < 1e-6);
+
+        let repeated = document(&embedder, "rust rust rust rust").await;
+        let weight = weight(&repeated, "rust");
+        assert!(weight > 1.0 && weight < K1 + 1.0, "{weight}");
+    }
+
+    #[tokio::test]
+    async fn longer_documents_weigh_words_less() {
+        let embedder = Bm25Embedder::new(4.0);
+        let short = document(&embedder, "rust").await;
+        let long = document(&embedder, "rust is a systems programming language").await;
+        assert!(weight(&short, "rust") > weight(&long, "rust"));
+    }
+
+    #[tokio::test]
+    async fn query_words_weigh_one() {
+        let vector = query("rust rust async").await;
+        assert_eq!(vector.indices.len(), 2);
+        assert!(vector.values.iter().all(|&value| value == 1.0));
+    }
+
+    #[tokio::test]
+    async fn without_words() {
+        assert!(document(&Bm25Embedder::default(), " ... ").await.is_empty());
+        assert!(query("").await.is_empty());
+        assert!(Bm25Embedder::default().idf());
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tonic::transport::{Channel, Endpoint};
+
+use super::{Embedder, SparseEmbedder, SparseVector};
+
+pub mod proto {
+    tonic::include_proto!("tei.v1");
+}
+
+use proto::{
+    embed_client::EmbedClient, info_client::InfoClient, EmbedRequest, EmbedResponse,
+    EmbedSparseRequest, EmbedSparseResponse, InfoRequest,
+};
+
+/// Embedder backed by the gRPC API of Text Embeddings Inference.
+pub struct TeiGrpcEmbedder {
+    client: EmbedClient<Channel>,
+    model_id: String,
+}
+
+impl TeiGrpcEmbedder {
+    pub async fn connect(uri: String) -> anyhow::Result<Self> {
+        let channel = Endpoint::from_shared(uri)
+            .context("Invalid TEI URI")?
+            .connect()
+            .await
+            .context("Failed to connect to TEI")?;
+        let info = InfoClient::new(channel.clone())
+            .info(InfoRequest {})
+            .await
+            .context("TEI info")?
+            .into_inner();
+        Ok(Self {
+            client: EmbedClient::new(channel),
+            model_id: info.model_id,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for TeiGrpcEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    async fn embed(&self, input: String) -> anyhow::Result<Vec<f32>> {
+        let EmbedResponse { embeddings, .. } = self
+            .client
+            .clone()
+            .embed(EmbedRequest {
+                inputs: input,
+                truncate: true,
+                normalize: true,
+                truncation_direction: 0,
+                prompt_name: None,
+            })
+            .await
+            .context("TEI embed")?
+            .into_inner();
+        Ok(embeddings)
+    }
+}
+
+/// Sparse embedder backed by a SPLADE model served by Text Embeddings Inference.
+pub struct TeiSparseEmbedder {
+    client: EmbedClient<Channel>,
+}
+
+impl TeiSparseEmbedder {
+    pub async fn connect(uri: String) -> anyhow::Result<Self> {
+        let client = EmbedClient::connect(uri)
+            .await
+            .context("Failed to connect to TEI")?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait::async_trait]
+impl SparseEmbedder for TeiSparseEmbedder {
+    async fn embed_document(&self, input: String) -> anyhow::Result<SparseVector> {
+        let EmbedSparseResponse {
+            mut sparse_embeddings,
+            ..
+        } = self
+            .client
+            .clone()
+            .embed_sparse(EmbedSparseRequest {
+                inputs: input,
+                truncate: true,
+                truncation_direction: 0,
+                prompt_name: None,
+            })
+            .await
+            .context("TEI embed sparse")?
+            .into_inner();
+        sparse_embeddings.sort_unstable_by_key(|value| value.index);
+        Ok(SparseVector {
+            indices: sparse_embeddings.iter().map(|value| value.index).collect(),
+            values: sparse_embeddings.iter().map(|value| value.value).collect(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct HttpEmbedRequest<'a> {
+    inputs: &'a [String],
+    truncate: bool,
+    normalize: bool,
+}
+
+#[derive(Deserialize)]
+struct HttpInfoResponse {
+    model_id: String,
+}
+
+/// Embedder backed by the HTTP API of Text Embeddings Inference.
+pub struct TeiHttpEmbedder {
+    client: reqwest::Client,
+    url: String,
+    model_id: String,
+}
+
+impl TeiHttpEmbedder {
+    pub async fn connect(uri: String) -> anyhow::Result<Self> {
+        let client = reqwest::Client::new();
+        let uri = uri.trim_end_matches('/');
+        let HttpInfoResponse { model_id } = client
+            .get(format!("{uri}/info"))
+            .send()
+            .await
+            .context("TEI info send")?
+            .error_for_status()
+            .context("TEI info response")?
+            .json()
+            .await
+            .context("TEI info body")?;
+        Ok(Self {
+            client,
+            url: format!("{uri}/embed"),
+            model_id,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for TeiHttpEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    async fn embed(&self, input: String) -> anyhow::Result<Vec<f32>> {
+        self.embed_batch(vec![input])
+            .await?
+            .pop()
+            .context("TEI returned no embedding")
+    }
+
+    async fn embed_batch(&self, inputs: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let request = HttpEmbedRequest {
+            inputs: &inputs,
+            truncate: true,
+            normalize: true,
+        };
+        let embeddings: Vec<Vec<f32>> = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await
+            .context("TEI embed send")?
+            .error_for_status()
+            .context("TEI embed response")?
+            .json()
+            .await
+            .context("TEI embed body")?;
+        anyhow::ensure!(
+            embeddings.len() == inputs.len(),
+            "TEI returned {} embeddings for {} inputs",
+            embeddings.len(),
+            inputs.len()
+        );
+        Ok(embeddings)
+    }
+}
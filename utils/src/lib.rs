@@ -16,6 +16,12 @@ pub mod redis;
 #[cfg(feature = "database")]
 pub mod database;
 
+#[cfg(feature = "embed")]
+pub mod embed;
+
+#[cfg(feature = "ltr")]
+pub mod ltr;
+
 fn get_resource(pkg: &str) -> Resource {
     Resource::new(vec![KeyValue::new(
         opentelemetry_semantic_conventions::resource::SERVICE_NAME,
@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Ranking features of a result, in the order of their values in feature vectors.
+///
+/// - `dense` - cosine similarity of the embeddings, 0 outside the dense ranking
+/// - `sparse` - sparse term vector score, 0 outside the sparse ranking
+/// - `freshness` - `1 / (1 + days since the last crawl / 30)`
+/// - `title_match` - share of the query words in the title
+/// - `domain_match` - share of the query words in the domain
+pub const FEATURES: [&str; 5] = [
+    "dense",
+    "sparse",
+    "freshness",
+    "title_match",
+    "domain_match",
+];
+
+/// Logistic regression over standardized features, estimating the probability of a
+/// result being clicked.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Model {
+    /// Names of the features the model was trained on, which must match [`FEATURES`].
+    pub features: Vec<String>,
+    pub weights: Vec<f32>,
+    pub bias: f32,
+    pub mean: Vec<f32>,
+    pub scale: Vec<f32>,
+}
+
+/// Labeled feature vector, `clicked` or shown above a clicked result without being so.
+pub struct Example {
+    pub features: Vec<f32>,
+    pub clicked: bool,
+}
+
+pub struct TrainConfig {
+    pub epochs: usize,
+    pub learning_rate: f32,
+    /// L2 regularization strength.
+    pub l2: f32,
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl Model {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read(path).context("Failed to read model")?;
+        let model: Self = serde_json::from_slice(&content).context("Failed to parse model")?;
+        anyhow::ensure!(
+            model.features.iter().map(String::as_str).eq(FEATURES),
+            "Model features {:?} do not match {FEATURES:?}",
+            model.features
+        );
+        anyhow::ensure!(
+            model.weights.len() == FEATURES.len()
+                && model.mean.len() == FEATURES.len()
+                && model.scale.len() == FEATURES.len(),
+            "Model has {} weights for {} features",
+            model.weights.len(),
+            FEATURES.len()
+        );
+        Ok(model)
+    }
+
+    /// Writes the model next to `path` and renames it over `path`, so that readers
+    /// polling the file never load a partly written model.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let content = serde_json::to_vec_pretty(self).context("Failed to serialize model")?;
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        std::fs::write(&temp, content).context("Failed to write model")?;
+        std::fs::rename(&temp, path).context("Failed to replace model")
+    }
+
+    /// Logit of the click probability of a result, only its order matters.
+    pub fn score(&self, features: &[f32]) -> f32 {
+        features
+            .iter()
+            .zip(&self.weights)
+            .zip(self.mean.iter().zip(&self.scale))
+            .map(|((x, w), (mean, scale))| w * (x - mean) / scale)
+            .sum::<f32>()
+            + self.bias
+    }
+
+    /// Fits the model to `examples` by full-batch gradient descent on the log loss.
+    pub fn train(examples: &[Example], config: &TrainConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(!examples.is_empty(), "No training examples");
+        anyhow::ensure!(
+            examples.iter().all(|e| e.features.len() == FEATURES.len()),
+            "Examples must have {} features",
+            FEATURES.len()
+        );
+        let n = examples.len() as f32;
+        let dimension = FEATURES.len();
+
+        let mut mean = vec![0.0; dimension];
+        for example in examples {
+            for (mean, x) in mean.iter_mut().zip(&example.features) {
+                *mean += x / n;
+            }
+        }
+        let mut scale = vec![0.0; dimension];
+        for example in examples {
+            for ((scale, x), mean) in scale.iter_mut().zip(&example.features).zip(&mean) {
+                *scale += (x - mean).powi(2) / n;
+            }
+        }
+        // Constant features keep a unit scale rather than dividing by 0.
+        let scale: Vec<f32> = scale
+            .into_iter()
+            .map(|variance: f32| {
+                let deviation = variance.sqrt();
+                if deviation > f32::EPSILON {
+                    deviation
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+
+        let mut model = Self {
+            features: FEATURES.iter().map(|f| f.to_string()).collect(),
+            weights: vec![0.0; dimension],
+            bias: 0.0,
+            mean,
+            scale,
+        };
+        for _ in 0..config.epochs {
+            let mut gradient = vec![0.0; dimension];
+            let mut bias_gradient = 0.0;
+            for example in examples {
+                let label = if example.clicked { 1.0 } else { 0.0 };
+                let error = sigmoid(model.score(&example.features)) - label;
+                for (i, gradient) in gradient.iter_mut().enumerate() {
+                    let x = (example.features[i] - model.mean[i]) / model.scale[i];
+                    *gradient += error * x / n;
+                }
+                bias_gradient += error / n;
+            }
+            for (weight, gradient) in model.weights.iter_mut().zip(gradient) {
+                *weight -= config.learning_rate * (gradient + config.l2 * *weight);
+            }
+            model.bias -= config.learning_rate * bias_gradient;
+        }
+        Ok(model)
+    }
+
+    /// Average log loss of the model on `examples`.
+    pub fn loss(&self, examples: &[Example]) -> f32 {
+        let total: f32 = examples
+            .iter()
+            .map(|example| {
+                let p = sigmoid(self.score(&example.features)).clamp(1e-6, 1.0 - 1e-6);
+                if example.clicked {
+                    -p.ln()
+                } else {
+                    -(1.0 - p).ln()
+                }
+            })
+            .sum();
+        total / examples.len().max(1) as f32
+    }
+}
@@ -1,6 +1,20 @@
 pub enum Key<'a> {
     Robots(&'a str),
     Cooldown(&'a str),
+    /// Cached query embedding, by digest of the model id and normalized query.
+    Embedding(&'a str),
+    /// Cached page of search results, by digest of the request and index version.
+    Results(&'a str),
+    /// Number of points upserted or deleted in the `pages` collection.
+    IndexChanges,
+    /// Sorted set of the normalized queries that returned results, by count.
+    Queries,
+    /// Token bucket of the rate limit of an API client or IP.
+    RateLimit(&'a str),
+    /// Set of the domains whose queues the selector skips.
+    PausedDomains,
+    /// List of the URLs of a paused job, queued again once it resumes.
+    DeferredUrls(&'a str),
 }
 
 impl redis::ToRedisArgs for Key<'_> {
@@ -11,6 +25,13 @@ impl redis::ToRedisArgs for Key<'_> {
         match self {
             Key::Robots(domain) => out.write_arg_fmt(format!("r:{domain}")),
             Key::Cooldown(domain) => out.write_arg_fmt(format!("c:{domain}")),
+            Key::Embedding(digest) => out.write_arg_fmt(format!("e:{digest}")),
+            Key::Results(digest) => out.write_arg_fmt(format!("s:{digest}")),
+            Key::IndexChanges => out.write_arg(b"ix"),
+            Key::Queries => out.write_arg(b"sq"),
+            Key::RateLimit(id) => out.write_arg_fmt(format!("rl:{id}")),
+            Key::PausedDomains => out.write_arg(b"pd"),
+            Key::DeferredUrls(job) => out.write_arg_fmt(format!("dj:{job}")),
         }
     }
 }